@@ -1,50 +1,81 @@
 use proc_macro2::{Punct, Spacing, TokenStream};
 use quote::{ToTokens, TokenStreamExt, quote};
-use syn::spanned::Spanned;
 use syn::{Attribute, Ident, Visibility};
 
-use crate::model::{EmptyVariant, Field, Model};
+use crate::model::{Encoding, Field, Model, NormalField, Variant};
 
 mod enumerate;
 mod fields;
+mod flags;
+mod shape;
 
 pub fn codegen(model: Model) -> TokenStream {
     match model.ty {
         crate::model::Type::NormalStruct(fields) => {
-            normal_struct(model.vis, model.ident, model.attrs, fields)
+            normal_struct(model.vis, model.ident, model.attrs, fields, false)
         }
-        crate::model::Type::UnitStruct(field) => {
-            unit_struct(model.vis, model.ident, model.attrs, field)
+        crate::model::Type::TupleStruct(fields) => {
+            normal_struct(model.vis, model.ident, model.attrs, fields, true)
         }
         crate::model::Type::Enum {
             variants,
             repr_type: repr,
             bits,
-        } => normal_enum(model.vis, model.ident, model.attrs, variants, repr, bits),
+            encoding,
+        } => enumeration(
+            model.vis,
+            model.ident,
+            model.attrs,
+            variants,
+            repr,
+            bits,
+            encoding,
+        ),
+        crate::model::Type::Flags {
+            bits,
+            flags,
+            repr_type: repr,
+        } => flags::flags(model.vis, model.ident, model.attrs, flags, repr, bits),
     }
 }
 
-fn normal_enum(
+fn enumeration(
     vis: Visibility,
     ident: Ident,
     attrs: Vec<Attribute>,
-    variants: Vec<EmptyVariant>,
+    variants: Vec<Variant>,
     repr: Ident,
     bits: usize,
+    encoding: Encoding,
 ) -> TokenStream {
-    let write_code = enumerate::write(repr.clone(), bits);
-    let read_code = enumerate::read(&variants, repr, bits);
+    // Once a variant holds data, even just the raw value, the discriminants can
+    // no longer be used to cast the enum to an integer. We leave them out so
+    // an `other` variant without a discriminant can be placed anywhere.
+    let keep_discriminants = variants.iter().all(enumerate::is_fieldless);
+    // Discriminants given as consts have the type of the repr we inferred
+    let has_repr = attrs.iter().any(|a| a.path().is_ident("repr"));
+    let repr_attr = (keep_discriminants && !has_repr).then(|| quote! { #[repr(#repr)] });
+    let variant_defs = variants
+        .iter()
+        .map(|v| enumerate::definition(v, keep_discriminants));
+    let write_code = enumerate::write(&ident, &variants, &repr, bits);
+    let read_code = enumerate::read(&repr, bits);
+    let tagged_impl = tagged_impl(&ident, &variants, bits, encoding);
 
     quote! {
         #(#attrs)*
+        #repr_attr
         #vis enum #ident {
-            #(#variants),*
+            #(#variant_defs),*
         }
 
         #[automatically_derived]
         impl ::abstract_bits::AbstractBits for #ident {
-            const MAX_BITS: usize = #bits;
-            const MIN_BITS: usize = #bits;
+            const MIN_BITS: usize = #bits + <Self as ::abstract_bits::Tagged>::MIN_BODY_BITS;
+            const MAX_BITS: usize = usize::saturating_add(
+                #bits,
+                <Self as ::abstract_bits::Tagged>::MAX_BODY_BITS,
+            );
 
             fn write_abstract_bits(&self, writer: &mut ::abstract_bits::BitWriter)
             -> Result<(), ::abstract_bits::ToBytesError> {
@@ -58,37 +89,53 @@ fn normal_enum(
                 #read_code
             }
         }
+
+        #tagged_impl
     }
 }
 
-fn unit_struct(
-    vis: Visibility,
-    ident: Ident,
-    attrs: Vec<Attribute>,
-    field: syn::Field,
+fn tagged_impl(
+    ident: &Ident,
+    variants: &[Variant],
+    bits: usize,
+    encoding: Encoding,
 ) -> TokenStream {
-    let field_ty = &field.ty;
-    quote! {
-        #(#attrs)*
-        #vis struct #ident(#field);
+    let min_body_bits = enumerate::min_body_bits(variants);
+    let max_body_bits = enumerate::max_body_bits(variants);
+    let tag_code = enumerate::tag(variants, encoding);
+    let write_body_code = enumerate::write_body(ident, variants);
+    let read_body_code = enumerate::read_body(ident, variants, encoding);
+    let has_variant_code = enumerate::has_variant(variants, encoding);
+    let raw_fits_tag = enumerate::raw_fits_tag(variants, bits);
+    let discriminants_valid = enumerate::discriminants_valid(variants, bits);
 
+    quote! {
         #[automatically_derived]
-        impl ::abstract_bits::AbstractBits for #ident {
-            const MAX_BITS: usize = <#field_ty as abstract_bits::AbstractBits>::MAX_BITS;
-            const MIN_BITS: usize = <#field_ty as abstract_bits::AbstractBits>::MIN_BITS;
+        impl ::abstract_bits::Tagged for #ident {
+            const MIN_BODY_BITS: usize = #min_body_bits;
+            const MAX_BODY_BITS: usize = #max_body_bits;
 
-            fn write_abstract_bits(&self, writer: &mut ::abstract_bits::BitWriter)
+            fn tag(&self) -> usize {
+                #tag_code
+            }
+            fn write_body(&self, writer: &mut ::abstract_bits::BitWriter)
             -> Result<(), ::abstract_bits::ToBytesError> {
-                self.0.write_abstract_bits(writer)
+                #write_body_code
             }
-            fn read_abstract_bits(reader: &mut ::abstract_bits::BitReader)
+            fn read_body(tag: usize, reader: &mut ::abstract_bits::BitReader)
             -> Result<Self, ::abstract_bits::FromBytesError>
             where
                 Self: Sized
             {
-                Ok(Self(<#field_ty>::read_abstract_bits(reader)?))
+                #read_body_code
+            }
+            fn has_variant(tag: usize) -> bool {
+                #has_variant_code
             }
         }
+
+        #raw_fits_tag
+        #discriminants_valid
     }
 }
 
@@ -97,26 +144,87 @@ fn normal_struct(
     ident: Ident,
     attrs: Vec<Attribute>,
     fields: Vec<Field>,
+    is_tuple: bool,
 ) -> TokenStream {
     let struct_fields: Vec<_> = fields
         .iter()
         .filter_map(Field::needed_in_struct_def)
         .collect();
-    let write_code: Vec<_> = fields.iter().map(|f| f.write_code(&ident)).collect();
-    let read_code: Vec<_> = fields.iter().map(|f| f.read_code(&ident)).collect();
+    let struct_def = if is_tuple {
+        quote! { #vis struct #ident(#(#struct_fields),*); }
+    } else {
+        quote! { #vis struct #ident { #(#struct_fields),* } }
+    };
+    let name = ident.to_string();
+    let write_code = with_checksums(
+        &fields,
+        fields.iter().map(|f| f.write_code(&name)).collect(),
+        quote! { writer.bits_written() },
+        fields.iter().filter_map(Field::fill_in_code).collect(),
+    );
+    let read_code = with_checksums(
+        &fields,
+        fields.iter().map(|f| f.read_code(&name)).collect(),
+        quote! { reader.bits_read() },
+        fields.iter().filter_map(|f| f.check_code(&name)).collect(),
+    );
     let min_bits_code: Vec<_> = fields.iter().map(Field::min_bits_code).collect();
     let max_bits_code: Vec<_> = fields.iter().map(Field::max_bits_code).collect();
-    let out_struct_idents: Vec<_> = fields
+    let out_struct_bindings: Vec<_> = struct_fields.iter().map(field_binding).collect();
+
+    let (write_body, read_body, last_marked_impl) = if fields
         .iter()
-        .filter_map(Field::needed_in_struct_def)
-        .map(|f| f.ident)
-        .collect();
+        .any(|f| matches!(f, Field::IsLast(_)))
+    {
+        let is_last = is_last_ident();
+        let last_marked_impl = quote! {
+            #[automatically_derived]
+            impl ::abstract_bits::LastMarked for #ident {
+                fn write_marked(&self, #is_last: bool, writer: &mut ::abstract_bits::BitWriter)
+                -> Result<(), ::abstract_bits::ToBytesError> {
+                    let Self { #(#out_struct_bindings),* } = self;
+                    #(#write_code)*
+                    Ok(())
+                }
+                fn read_marked(reader: &mut ::abstract_bits::BitReader)
+                -> Result<(Self, bool), ::abstract_bits::FromBytesError>
+                where
+                    Self: Sized
+                {
+                    #(#read_code)*
+                    Ok((Self {
+                        #(#out_struct_bindings),*
+                    }, #is_last))
+                }
+            }
+        };
+        // on its own an element is the last one
+        let write_body = quote! {
+            ::abstract_bits::LastMarked::write_marked(self, true, writer)
+        };
+        let read_body = quote! {
+            <Self as ::abstract_bits::LastMarked>::read_marked(reader)
+                .map(|(element, _)| element)
+        };
+        (write_body, read_body, last_marked_impl)
+    } else {
+        let write_body = quote! {
+            let Self { #(#out_struct_bindings),* } = self;
+            #(#write_code)*
+            Ok(())
+        };
+        let read_body = quote! {
+            #(#read_code)*
+            Ok(Self {
+                #(#out_struct_bindings),*
+            })
+        };
+        (write_body, read_body, quote! {})
+    };
 
     quote! {
         #(#attrs)*
-        #vis struct #ident {
-            #(#struct_fields),*
-        }
+        #struct_def
 
         #[automatically_derived]
         impl ::abstract_bits::AbstractBits for #ident {
@@ -127,27 +235,57 @@ fn normal_struct(
             };
             const MAX_BITS: usize = const {
                 let mut max = 0;
-                #(max += #max_bits_code;)*
+                #(max = usize::saturating_add(max, #max_bits_code);)*
                 max
             };
 
             fn write_abstract_bits(&self, writer: &mut ::abstract_bits::BitWriter)
             -> Result<(), ::abstract_bits::ToBytesError> {
-                #(#write_code)*
-                Ok(())
+                #write_body
             }
             fn read_abstract_bits(reader: &mut ::abstract_bits::BitReader)
             -> Result<Self, ::abstract_bits::FromBytesError>
             where
                 Self: Sized
             {
-                #(#read_code)*
-                Ok(Self {
-                    #(#out_struct_idents),*
-                })
+                #read_body
             }
         }
+
+        #last_marked_impl
+    }
+}
+
+/// Records the position of every field a checksum starts or ends at in a local
+/// variable, then appends `finish` which uses them
+fn with_checksums(
+    fields: &[Field],
+    code: Vec<TokenStream>,
+    position: TokenStream,
+    finish: Vec<TokenStream>,
+) -> Vec<TokenStream> {
+    let boundaries: Vec<usize> = fields
+        .iter()
+        .filter_map(|f| match f {
+            Field::Checksum { covers, .. } => Some([covers.start, covers.end]),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    let mark = |i: usize| {
+        boundaries.contains(&i).then(|| {
+            let ident = position_ident(i);
+            quote! { let #ident = #position; }
+        })
+    };
+    let mut res = Vec::new();
+    for (i, code) in code.into_iter().enumerate() {
+        res.extend(mark(i));
+        res.push(code);
     }
+    res.extend(mark(fields.len()));
+    res.extend(finish);
+    res
 }
 
 impl ToTokens for super::model::NormalField {
@@ -157,22 +295,21 @@ impl ToTokens for super::model::NormalField {
         }
         self.vis.to_tokens(tokens);
 
-        self.ident.to_tokens(tokens);
-        tokens.append(Punct::new(':', Spacing::Joint));
+        if let syn::Member::Named(ident) = &self.member {
+            ident.to_tokens(tokens);
+            tokens.append(Punct::new(':', Spacing::Joint));
+        }
 
         self.out_ty.to_tokens(tokens);
     }
 }
 
-impl ToTokens for super::model::EmptyVariant {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        for attr in &self.attrs {
-            attr.to_tokens(tokens);
-        }
-        self.ident.to_tokens(tokens);
-        tokens.append(Punct::new('=', Spacing::Joint));
-
-        proc_macro2::Literal::usize_unsuffixed(self.discriminant).to_tokens(tokens)
+/// Binds a field to its local variable in a struct pattern or expression
+fn field_binding(field: &NormalField) -> TokenStream {
+    let NormalField { member, ident, .. } = field;
+    match member {
+        syn::Member::Named(_) => quote! { #ident },
+        syn::Member::Unnamed(_) => quote! { #member: #ident },
     }
 }
 
@@ -180,6 +317,25 @@ pub fn list_len_ident(controlled: &Ident) -> Ident {
     quote::format_ident!("{controlled}_len")
 }
 
+pub fn tag_ident(controlled: &Ident) -> Ident {
+    quote::format_ident!("{controlled}_tag")
+}
+
+/// Bits read or written before the field at `index`
+pub fn position_ident(index: usize) -> Ident {
+    quote::format_ident!("bits_before_field_{index}")
+}
+
+/// The stored checksum when reading, its position when writing
+pub fn checksum_ident(index: usize) -> Ident {
+    quote::format_ident!("checksum_{index}")
+}
+
+/// Parameter of `LastMarked::write_marked`, returned by `read_marked`
+pub fn is_last_ident() -> Ident {
+    quote::format_ident!("is_last_element")
+}
+
 pub fn is_primitive(bits: usize) -> Option<TokenStream> {
     match bits {
         8 => Some(quote! {u8}),
@@ -189,20 +345,3 @@ pub fn is_primitive(bits: usize) -> Option<TokenStream> {
         _ => None,
     }
 }
-
-/// Turns `Type<T>` into `Type::<T>` which is needed for
-/// `Type::<T>::read_abstract_bits(reader)`
-pub fn generics_to_fully_qualified(mut ty: syn::Type) -> syn::Type {
-    if let syn::Type::Path(typath) = &mut ty {
-        let syn::Path { segments, .. } = &mut typath.path;
-        let first_seg = segments
-            .first_mut()
-            .expect("type path always has at least one segment");
-        let syn::PathArguments::AngleBracketed(args) = &mut first_seg.arguments else {
-            return ty;
-        };
-
-        args.colon2_token = Some(syn::Token![::](args.span()));
-    }
-    ty
-}
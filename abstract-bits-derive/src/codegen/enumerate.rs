@@ -1,57 +1,528 @@
-use proc_macro2::TokenStream;
-use quote::{quote_spanned, quote};
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
 use syn::Ident;
+use syn::spanned::Spanned;
 
-use crate::model::EmptyVariant;
+use crate::model::{Discriminant, Encoding, Field, Variant, VariantFields};
 
-use super::is_primitive;
+use super::{field_binding, is_primitive};
 
-pub(crate) fn write(repr: Ident, bits: usize) -> TokenStream {
-    if is_primitive(bits).is_some() {
+pub fn definition(variant: &Variant, keep_discriminant: bool) -> TokenStream {
+    let Variant {
+        attrs,
+        ident,
+        discriminant,
+        fields: variant_fields,
+    } = variant;
+    let fields = variant_fields
+        .fields()
+        .iter()
+        .filter_map(Field::needed_in_struct_def);
+    let fields = match (variant_fields, discriminant) {
+        (_, Discriminant::Other(raw_ty))
+        | (
+            _,
+            Discriminant::Range {
+                raw: Some(raw_ty), ..
+            },
+        ) => quote! { (#raw_ty) },
+        (VariantFields::Unit, _) => quote! {},
+        (VariantFields::Named(_), _) => quote! { { #(#fields),* } },
+        (VariantFields::Unnamed(_), _) => quote! { ( #(#fields),* ) },
+    };
+    let discriminant = match discriminant {
+        _ if !keep_discriminant => quote! {},
+        // a range casts to the value it is written as
+        Discriminant::Value(value) | Discriminant::Range { start: value, .. } => {
+            let value = Literal::usize_unsuffixed(*value);
+            quote! { = #value }
+        }
+        Discriminant::Expr { expr, offset: 0 } => quote! { = #expr },
+        // the compiler counts up from the expression like we do
+        Discriminant::Expr { .. } | Discriminant::Other(_) => quote! {},
+    };
+    quote! {
+        #(#attrs)*
+        #ident #fields #discriminant
+    }
+}
+
+/// The wire type follows `bits`, not the repr
+fn read_discriminant(repr: &Ident, bits: usize) -> TokenStream {
+    if let Some(ty) = is_primitive(bits) {
         quote_spanned! {repr.span()=>
-            ::abstract_bits::AbstractBits::write_abstract_bits(&(*self as #repr), writer)
+            let discriminant = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)?;
         }
     } else {
-        let utype: syn::Type =
-            syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path");
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
         quote_spanned! {repr.span()=>
-            let discriminant = #utype::new(*self as #repr);
-            ::abstract_bits::AbstractBits::write_abstract_bits(&discriminant, writer)
+            let discriminant = #utype::read_abstract_bits(reader)?;
+            let discriminant = discriminant.value();
         }
     }
 }
 
-pub fn read(variants: &[EmptyVariant], repr: Ident, bits: usize) -> TokenStream {
-    let variants_discriminants = variants
+fn invalid_discriminant() -> TokenStream {
+    quote! {
+        invalid => Err(::abstract_bits::FromBytesError::ReadEnum {
+            enum_name: std::any::type_name::<Self>(),
+            cause: ::abstract_bits::ReadErrorCause::InvalidDiscriminant {
+                ty: std::any::type_name::<Self>(),
+                got: invalid as usize,
+            }
+        }),
+    }
+}
+
+fn not_encoded(cause: TokenStream) -> TokenStream {
+    quote! {
+        return Err(::abstract_bits::FromBytesError::ReadEnum {
+            enum_name: std::any::type_name::<Self>(),
+            cause: ::abstract_bits::ReadErrorCause::#cause {
+                ty: std::any::type_name::<Self>(),
+                got: discriminant,
+            }
+        });
+    }
+}
+
+fn variant_name(enum_ident: &Ident, variant: &Variant) -> String {
+    format!("{enum_ident}::{}", variant.ident)
+}
+
+/// Pattern or expression binding every field of the variant to a local
+fn variant_bindings(variant: &Variant) -> TokenStream {
+    let ident = &variant.ident;
+    let bindings = variant
+        .fields
+        .fields()
         .iter()
-        .map(|v| v.discriminant)
-        .map(proc_macro2::Literal::usize_unsuffixed);
-    let variant_idents = variants.iter().map(|v| &v.ident);
+        .filter_map(Field::needed_in_struct_def)
+        .map(|f| field_binding(&f));
+    quote! { Self::#ident { #(#bindings),* } }
+}
+
+/// Holds no data, not even the raw value, so it can be cast to an integer
+pub fn is_fieldless(variant: &Variant) -> bool {
+    matches!(variant.fields, VariantFields::Unit) && raw_type(variant).is_none()
+}
+
+fn other_variant(variants: &[Variant]) -> Option<&Variant> {
+    variants
+        .iter()
+        .find(|v| matches!(v.discriminant, Discriminant::Other(_)))
+}
+
+/// Type of the raw value held by `other` and `range` variants
+fn raw_type(variant: &Variant) -> Option<&syn::Type> {
+    match &variant.discriminant {
+        Discriminant::Other(raw) => Some(raw),
+        Discriminant::Range { raw, .. } => raw.as_deref(),
+        Discriminant::Value(_) | Discriminant::Expr { .. } => None,
+    }
+}
+
+/// Expression evaluating to a discriminant only known to the compiler
+fn expr_value(expr: &syn::Expr, offset: usize) -> TokenStream {
+    quote_spanned! {expr.span()=> (#expr) as usize + #offset }
+}
+
+pub fn tag(variants: &[Variant], encoding: Encoding) -> TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        match variant.discriminant {
+            Discriminant::Value(value) => {
+                let value = Literal::usize_unsuffixed(value);
+                quote! { Self::#ident { .. } => #value, }
+            }
+            Discriminant::Expr { ref expr, offset } => {
+                let value = expr_value(expr, offset);
+                quote! { Self::#ident { .. } => #value, }
+            }
+            Discriminant::Other(_) | Discriminant::Range { raw: Some(_), .. } => {
+                quote! { Self::#ident(raw) => *raw as usize, }
+            }
+            Discriminant::Range {
+                start, raw: None, ..
+            } => {
+                let start = Literal::usize_unsuffixed(start);
+                quote! { Self::#ident => #start, }
+            }
+        }
+    });
+    let index = quote! {
+        match self {
+            #(#arms)*
+        }
+    };
+    // the tag is the code on the wire, not the variant index
+    match encoding {
+        Encoding::Binary => index,
+        Encoding::OneHot => quote! {
+            let index: usize = #index;
+            1 << index
+        },
+        Encoding::Thermometer => quote! {
+            let index: usize = #index;
+            usize::MAX.checked_shr(usize::BITS - index as u32).unwrap_or(0)
+        },
+    }
+}
+
+pub fn write_body(enum_ident: &Ident, variants: &[Variant]) -> TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let name = variant_name(enum_ident, variant);
+        let ident = &variant.ident;
+        let pattern = match variant.discriminant {
+            Discriminant::Value(_) | Discriminant::Expr { .. } => {
+                variant_bindings(variant)
+            }
+            Discriminant::Other(_) => {
+                let enum_name = Literal::string(&enum_ident.to_string());
+                let variant_name = Literal::string(&ident.to_string());
+                let known = known_discriminant(variants);
+                // it would be read back as that variant
+                return quote! {
+                    Self::#ident(raw) => {
+                        let raw = *raw as usize;
+                        if #known {
+                            return Err(::abstract_bits::ToBytesError::KnownDiscriminant {
+                                field_name: #variant_name,
+                                struct_name: #enum_name,
+                                got: raw as u64,
+                            });
+                        }
+                    }
+                };
+            }
+            Discriminant::Range {
+                start,
+                end,
+                raw: Some(_),
+            } => {
+                let (min, max) = (start as u64, end as u64);
+                let enum_name = Literal::string(&enum_ident.to_string());
+                let variant_name = Literal::string(&ident.to_string());
+                return quote! {
+                    Self::#ident(raw) => {
+                        if !(#min..=#max).contains(&(*raw as u64)) {
+                            return Err(::abstract_bits::ToBytesError::ValueOutOfRange {
+                                field_name: #variant_name,
+                                struct_name: #enum_name,
+                                min: #min,
+                                max: #max,
+                                got: *raw as u64,
+                            });
+                        }
+                    }
+                };
+            }
+            Discriminant::Range { raw: None, .. } => quote! { Self::#ident },
+        };
+        let write_code = variant.fields.fields().iter().map(|f| f.write_code(&name));
+        quote! {
+            #pattern => {
+                #(#write_code)*
+            }
+        }
+    });
+
+    quote! {
+        match self {
+            #(#arms)*
+        }
+        Ok(())
+    }
+}
+
+/// Whether a variant is read for `tag`, an `other` variant takes any tag
+pub fn has_variant(variants: &[Variant], encoding: Encoding) -> TokenStream {
+    if other_variant(variants).is_some() {
+        return quote! { let _ = tag; true };
+    }
+    let decode = match encoding {
+        Encoding::Binary => quote! {},
+        Encoding::OneHot => quote! {
+            if tag.count_ones() != 1 {
+                return false;
+            }
+            let tag = tag.trailing_zeros() as usize;
+        },
+        Encoding::Thermometer => quote! {
+            if tag & tag.wrapping_add(1) != 0 {
+                return false;
+            }
+            let tag = tag.count_ones() as usize;
+        },
+    };
+    let known = known_discriminant(variants);
+    quote! {
+        #decode
+        let raw = tag;
+        #known
+    }
+}
+
+/// Whether `raw` matches one of the variants
+fn known_discriminant(variants: &[Variant]) -> TokenStream {
+    let checks = variants.iter().filter_map(|variant| {
+        Some(match variant.discriminant {
+            Discriminant::Value(value) => {
+                let value = Literal::usize_unsuffixed(value);
+                quote! { raw == #value }
+            }
+            Discriminant::Expr { ref expr, offset } => {
+                let value = expr_value(expr, offset);
+                quote! { raw == #value }
+            }
+            Discriminant::Range { start, end, .. } => {
+                let start = Literal::usize_unsuffixed(start);
+                let end = Literal::usize_unsuffixed(end);
+                quote! { (#start..=#end).contains(&raw) }
+            }
+            Discriminant::Other(_) => return None,
+        })
+    });
+    quote! { false #(|| #checks)* }
+}
+
+pub fn read_body(
+    enum_ident: &Ident,
+    variants: &[Variant],
+    encoding: Encoding,
+) -> TokenStream {
+    let arms = variants.iter().filter_map(|variant| {
+        let ident = &variant.ident;
+        let pattern = match variant.discriminant {
+            Discriminant::Value(value) => {
+                let value = Literal::usize_unsuffixed(value);
+                quote! { #value }
+            }
+            Discriminant::Expr { ref expr, offset } => {
+                let value = expr_value(expr, offset);
+                quote! { tag if tag == #value }
+            }
+            Discriminant::Other(_) => return None,
+            Discriminant::Range {
+                start,
+                end,
+                ref raw,
+            } => {
+                let start = Literal::usize_unsuffixed(start);
+                let end = Literal::usize_unsuffixed(end);
+                let construct = match raw {
+                    Some(_) => quote! { Self::#ident(tag as _) },
+                    None => quote! { Self::#ident },
+                };
+                return Some(quote! { #start..=#end => Ok(#construct), });
+            }
+        };
+        let name = variant_name(enum_ident, variant);
+        let construct = variant_bindings(variant);
+        let read_code = variant.fields.fields().iter().map(|f| f.read_code(&name));
+        Some(quote! {
+            #pattern => {
+                #(#read_code)*
+                Ok(#construct)
+            }
+        })
+    });
+    let fallback = match other_variant(variants) {
+        Some(Variant { ident, .. }) => quote! { raw => Ok(Self::#ident(raw as _)), },
+        None => invalid_discriminant(),
+    };
+
+    let decode = match encoding {
+        Encoding::Binary => quote! {},
+        Encoding::OneHot => {
+            let error = not_encoded(quote! { NotOneHot });
+            quote! {
+                let discriminant = tag as u64;
+                if discriminant.count_ones() != 1 {
+                    #error
+                }
+                let tag = discriminant.trailing_zeros() as usize;
+            }
+        }
+        Encoding::Thermometer => {
+            let error = not_encoded(quote! { NotThermometer });
+            quote! {
+                let discriminant = tag as u64;
+                if discriminant & discriminant.wrapping_add(1) != 0 {
+                    #error
+                }
+                let tag = discriminant.count_ones() as usize;
+            }
+        }
+    };
+
+    quote! {
+        #decode
+        match tag {
+            #(#arms)*
+            #fallback
+        }
+    }
+}
 
-    let read_discriminant = if is_primitive(bits).is_some() {
+/// The discriminant is followed by the fields of the variant
+pub fn write(
+    enum_ident: &Ident,
+    variants: &[Variant],
+    repr: &Ident,
+    bits: usize,
+) -> TokenStream {
+    // The raw value of an `other` variant could be too large
+    let check_fits = other_variant(variants).map(|other| {
+        let enum_name = Literal::string(&enum_ident.to_string());
+        let variant_name = Literal::string(&other.ident.to_string());
+        let max = Literal::u64_suffixed(u64::MAX >> (64 - bits));
+        quote! {
+            if tag as u64 > #max {
+                return Err(::abstract_bits::ToBytesError::ValueTooLarge {
+                    field_name: #variant_name,
+                    struct_name: #enum_name,
+                    max: #max,
+                    got: tag as u64,
+                });
+            }
+        }
+    });
+    // the wire type follows `bits`, not the repr
+    let write_discriminant = if let Some(ty) = is_primitive(bits) {
         quote_spanned! {repr.span()=>
-            let discriminant = #repr::read_abstract_bits(reader)?;
+            let discriminant = tag as #ty;
+            ::abstract_bits::AbstractBits::write_abstract_bits(&discriminant, writer)?;
         }
     } else {
-        let utype: syn::Type =
-            syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path");
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
         quote_spanned! {repr.span()=>
-            let discriminant = #utype::read_abstract_bits(reader)?;
-            let discriminant = discriminant.value();
+            let discriminant = #utype::new(tag as _);
+            ::abstract_bits::AbstractBits::write_abstract_bits(&discriminant, writer)?;
         }
     };
+    quote! {
+        let tag = ::abstract_bits::Tagged::tag(self);
+        #check_fits
+        #write_discriminant
+        ::abstract_bits::Tagged::write_body(self, writer)
+    }
+}
 
+pub fn read(repr: &Ident, bits: usize) -> TokenStream {
+    let read_discriminant = read_discriminant(repr, bits);
     quote! {
-        #read_discriminant;
-        match discriminant {
-            #(#variants_discriminants => Ok(Self::#variant_idents)),*,
-            invalid => Err(::abstract_bits::FromBytesError::ReadEnum { 
-                enum_name: std::any::type_name::<Self>(), 
-                cause: ::abstract_bits::ReadErrorCause::InvalidDiscriminant {
-                    ty: std::any::type_name::<Self>(),
-                    got: discriminant as usize,
-                }
-            }),
+        #read_discriminant
+        <Self as ::abstract_bits::Tagged>::read_body(discriminant as usize, reader)
+    }
+}
+
+/// Compile time check that the raw values held by variants can hold every
+/// discriminant
+pub fn raw_fits_tag(variants: &[Variant], bits: usize) -> TokenStream {
+    let checks = variants.iter().filter_map(raw_type).map(|raw_ty| {
+        quote_spanned! {raw_ty.span()=>
+            const _: () = assert!(
+                <#raw_ty as ::abstract_bits::AbstractBits>::MAX_BITS >= #bits,
+                "the raw value held by a variant can not hold every discriminant"
+            );
         }
+    });
+    quote! { #(#checks)* }
+}
+
+/// Discriminants only known to the compiler are checked once it knows them
+pub fn discriminants_valid(variants: &[Variant], bits: usize) -> Option<TokenStream> {
+    if !variants
+        .iter()
+        .any(|v| matches!(v.discriminant, Discriminant::Expr { .. }))
+    {
+        return None;
     }
+    let values = variants
+        .iter()
+        .filter_map(|variant| match &variant.discriminant {
+            Discriminant::Value(value) => Some(quote! { #value }),
+            Discriminant::Expr { expr, offset } => Some(expr_value(expr, *offset)),
+            Discriminant::Other(_) | Discriminant::Range { .. } => None,
+        });
+    let ranges = variants
+        .iter()
+        .filter_map(|variant| match variant.discriminant {
+            Discriminant::Range { start, end, .. } => Some(quote! { (#start, #end) }),
+            _ => None,
+        });
+    Some(quote! {
+        const _: () = {
+            let values: &[usize] = &[#(#values),*];
+            let ranges: &[(usize, usize)] = &[#(#ranges),*];
+            let mut i = 0;
+            while i < values.len() {
+                assert!(
+                    (values[i] as u128) < 1 << #bits,
+                    "a discriminant does not fit into the number of bits"
+                );
+                let mut j = i + 1;
+                while j < values.len() {
+                    assert!(values[i] != values[j], "two variants have the same discriminant");
+                    j += 1;
+                }
+                let mut r = 0;
+                while r < ranges.len() {
+                    assert!(
+                        values[i] < ranges[r].0 || values[i] > ranges[r].1,
+                        "a discriminant overlaps with a range variant"
+                    );
+                    r += 1;
+                }
+                i += 1;
+            }
+        };
+    })
+}
+
+/// Size of the smallest variant body
+pub fn min_body_bits(variants: &[Variant]) -> TokenStream {
+    let bodies = variants.iter().map(|variant| {
+        let min_bits_code = variant.fields.fields().iter().map(Field::min_bits_code);
+        quote! {{
+            let mut sum = 0;
+            #(sum += #min_bits_code;)*
+            sum
+        }}
+    });
+    quote! {{
+        let mut min = usize::MAX;
+        #(
+            let body = #bodies;
+            if body < min {
+                min = body;
+            }
+        )*
+        min
+    }}
+}
+
+/// Size of the largest variant body
+pub fn max_body_bits(variants: &[Variant]) -> TokenStream {
+    let bodies = variants.iter().map(|variant| {
+        let max_bits_code = variant.fields.fields().iter().map(Field::max_bits_code);
+        quote! {{
+            let mut sum = 0;
+            #(sum = usize::saturating_add(sum, #max_bits_code);)*
+            sum
+        }}
+    });
+    quote! {{
+        let mut max = 0;
+        #(
+            let body = #bodies;
+            if body > max {
+                max = body;
+            }
+        )*
+        max
+    }}
 }
@@ -1,76 +1,296 @@
-use crate::model::Field;
+use crate::model::{Field, ListLength};
 use proc_macro2::TokenStream;
 
 mod array;
+mod checksum;
 mod control_list;
 mod control_option;
+mod control_tag;
+mod is_last;
 mod list;
+mod magic;
 mod normal;
 mod option;
 mod padding;
+mod prefixed_list;
+mod presence_bitmap;
+mod rest_list;
+mod tagged;
+mod terminated_list;
 
 impl Field {
-    pub fn read_code(&self, struct_ident: &syn::Ident) -> TokenStream {
-        let struct_name = proc_macro2::Literal::string(&struct_ident.to_string());
+    pub fn read_code(&self, struct_name: &str) -> TokenStream {
+        let struct_name = proc_macro2::Literal::string(struct_name);
         match self {
+            Field::Checksum { bits, index, .. } => {
+                checksum::read(*bits, *index, &struct_name)
+            }
+            Field::Magic { bits, value } => magic::read(*bits, *value, &struct_name),
             Field::Normal(normal_field) => normal::read(normal_field, &struct_name),
             Field::PaddBits(n_bits) => padding::read(*n_bits, &struct_name),
-            Field::ControlList { controlled, bits } => {
-                control_list::read(controlled, *bits, &struct_name)
+            Field::ControlList {
+                controlled,
+                shared,
+                bits,
+                transform,
+                kept,
+            } => control_list::read(
+                controlled,
+                shared,
+                *bits,
+                transform.as_ref(),
+                kept.as_ref(),
+                &struct_name,
+            ),
+            Field::ControlOption {
+                controlled,
+                bits,
+                present,
+                kept,
+                ..
+            } => control_option::read(
+                controlled,
+                *bits,
+                present,
+                kept.as_ref(),
+                &struct_name,
+            ),
+            Field::PresenceBitmap { controlled, bits } => {
+                presence_bitmap::read(controlled, *bits, &struct_name)
             }
-            Field::ControlOption(ident) => control_option::read(ident, &struct_name),
-            Field::Option { inner_type, .. } => option::read(inner_type, &struct_name),
-            Field::List { inner_type, .. } => list::read(inner_type, &struct_name),
+            Field::ControlTag {
+                controlled,
+                bits,
+                kept,
+            } => control_tag::read(controlled, *bits, kept.as_ref(), &struct_name),
+            Field::Tagged(field) => tagged::read(field, &struct_name),
+            Field::Option {
+                inner_type,
+                presence,
+                ..
+            } => option::read(inner_type, presence, &struct_name),
+            Field::List {
+                full_type,
+                inner_type,
+                element,
+                length,
+            } => match length {
+                ListLength::Controlled { .. } => {
+                    list::read(inner_type, element, &struct_name)
+                }
+                ListLength::Rest => rest_list::read(full_type, element, &struct_name),
+                ListLength::Prefixed { bits } => {
+                    prefixed_list::read(full_type, element, *bits, &struct_name)
+                }
+                ListLength::Terminator(terminator) => terminated_list::read_terminator(
+                    full_type,
+                    inner_type,
+                    element,
+                    terminator,
+                    &struct_name,
+                ),
+                ListLength::UntilLast => {
+                    terminated_list::read_until_last(full_type, inner_type, &struct_name)
+                }
+            },
+            Field::IsLast(member) => is_last::read(member, &struct_name),
             Field::Array {
                 length,
                 inner_type,
+                element,
                 field,
-            } => array::read(length, inner_type, field, &struct_name),
+            } => array::read(length, inner_type, element, field, &struct_name),
         }
     }
 
-    pub fn write_code(&self, struct_ident: &syn::Ident) -> TokenStream {
-        let struct_name = proc_macro2::Literal::string(&struct_ident.to_string());
+    pub fn write_code(&self, struct_name: &str) -> TokenStream {
+        let struct_name = proc_macro2::Literal::string(struct_name);
         match self {
-            Field::Normal(normal_field) => normal::write(normal_field),
+            Field::Checksum { bits, index, .. } => {
+                checksum::write(*bits, *index, &struct_name)
+            }
+            Field::Magic { bits, value } => magic::write(*bits, *value),
+            Field::Normal(normal_field) => normal::write(normal_field, &struct_name),
             Field::PaddBits(n_bits) => padding::write(*n_bits, &struct_name),
-            Field::ControlList { controlled, bits } => control_list::write(controlled, *bits),
-            Field::ControlOption(controlled) => control_option::write(controlled),
-            Field::Option { inner_type, .. } => option::write(inner_type),
-            Field::List { inner_type, .. } => list::write(inner_type),
-            Field::Array { field, .. } => array::write(field),
+            Field::ControlList {
+                controlled,
+                shared,
+                bits,
+                transform,
+                kept,
+            } => control_list::write(
+                controlled,
+                shared,
+                *bits,
+                transform.as_ref(),
+                kept.as_ref(),
+                &struct_name,
+            ),
+            Field::ControlOption {
+                controlled,
+                bits,
+                present,
+                absent,
+                kept,
+            } => control_option::write(
+                controlled,
+                *bits,
+                present,
+                *absent,
+                kept.as_ref(),
+                &struct_name,
+            ),
+            Field::PresenceBitmap { controlled, bits } => {
+                presence_bitmap::write(controlled, *bits)
+            }
+            Field::ControlTag {
+                controlled,
+                bits,
+                kept,
+            } => control_tag::write(controlled, *bits, kept.as_ref(), &struct_name),
+            Field::Tagged(field) => tagged::write(field),
+            Field::Option {
+                inner_type,
+                presence,
+                ..
+            } => option::write(inner_type, presence, &struct_name),
+            Field::List {
+                inner_type,
+                element,
+                length,
+                ..
+            } => match length {
+                ListLength::Controlled { .. } | ListLength::Rest => {
+                    list::write(inner_type, element, &struct_name)
+                }
+                ListLength::Prefixed { bits } => {
+                    prefixed_list::write(inner_type, element, *bits, &struct_name)
+                }
+                ListLength::Terminator(terminator) => terminated_list::write_terminator(
+                    inner_type,
+                    element,
+                    terminator,
+                    &struct_name,
+                ),
+                ListLength::UntilLast => {
+                    terminated_list::write_until_last(inner_type, &struct_name)
+                }
+            },
+            Field::IsLast(member) => is_last::write(member),
+            Field::Array {
+                element,
+                length,
+                field,
+                ..
+            } => array::write(element, length, field, &struct_name),
+        }
+    }
+
+    /// Runs once all fields are read, checks a checksum
+    pub fn check_code(&self, struct_name: &str) -> Option<TokenStream> {
+        let struct_name = proc_macro2::Literal::string(struct_name);
+        match self {
+            Field::Checksum {
+                algorithm,
+                bits,
+                index,
+                covers,
+                ..
+            } => Some(checksum::check(
+                algorithm,
+                *bits,
+                *index,
+                covers,
+                &struct_name,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Runs once all fields are written, fills in a checksum
+    pub fn fill_in_code(&self) -> Option<TokenStream> {
+        match self {
+            Field::Checksum {
+                algorithm,
+                bits,
+                index,
+                covers,
+                ..
+            } => Some(checksum::fill_in(algorithm, *bits, *index, covers)),
+            _ => None,
         }
     }
 
     pub fn min_bits_code(&self) -> TokenStream {
         match self {
+            Field::Checksum { bits, .. } => checksum::min_bits(*bits),
+            Field::Magic { bits, .. } => magic::min_bits(*bits),
             Field::Normal(normal_field) => normal::min_bits(normal_field),
             Field::PaddBits(n_bits) => padding::min_bits(*n_bits),
             Field::ControlList { bits, .. } => control_list::min_bits(*bits),
-            Field::ControlOption(_) => control_option::min_bits(),
-            Field::Option { inner_type, .. } => option::min_bits(inner_type),
-            Field::List { inner_type, .. } => list::min_bits(inner_type),
+            Field::ControlOption { bits, .. } => control_option::min_bits(*bits),
+            Field::PresenceBitmap { bits, .. } => presence_bitmap::min_bits(*bits),
+            Field::ControlTag { bits, .. } => control_tag::min_bits(*bits),
+            Field::Tagged(field) => tagged::min_bits(field),
+            Field::Option {
+                inner_type,
+                presence,
+                ..
+            } => option::min_bits(inner_type, presence),
+            Field::List {
+                inner_type,
+                element,
+                length,
+                ..
+            } => match length {
+                ListLength::Controlled { .. } | ListLength::Rest => {
+                    list::min_bits(inner_type)
+                }
+                ListLength::Prefixed { bits } => prefixed_list::min_bits(*bits),
+                ListLength::Terminator(_) | ListLength::UntilLast => {
+                    terminated_list::min_bits(element)
+                }
+            },
+            Field::IsLast(_) => is_last::min_bits(),
             Field::Array {
-                inner_type, length, ..
-            } => array::min_bits(inner_type, length),
+                element, length, ..
+            } => array::min_bits(element, length),
         }
     }
 
     pub fn max_bits_code(&self) -> TokenStream {
         match self {
+            Field::Checksum { bits, .. } => checksum::max_bits(*bits),
+            Field::Magic { bits, .. } => magic::max_bits(*bits),
             Field::Normal(normal_field) => normal::max_bits(normal_field),
             Field::PaddBits(n_bits) => padding::max_bits(*n_bits),
             Field::ControlList { bits, .. } => control_list::max_bits(*bits),
-            Field::ControlOption(_) => control_option::max_bits(),
+            Field::ControlOption { bits, .. } => control_option::max_bits(*bits),
+            Field::PresenceBitmap { bits, .. } => presence_bitmap::max_bits(*bits),
+            Field::ControlTag { bits, .. } => control_tag::max_bits(*bits),
+            Field::Tagged(field) => tagged::max_bits(field),
             Field::Option { inner_type, .. } => option::max_bits(inner_type),
             Field::List {
                 inner_type,
-                max_len,
+                element,
+                length,
                 ..
-            } => list::max_bits(inner_type, *max_len),
+            } => match length {
+                ListLength::Controlled { bits, transform } => {
+                    list::max_bits(inner_type, element, *bits, transform.as_deref())
+                }
+                ListLength::Rest => rest_list::max_bits(),
+                ListLength::Prefixed { bits } => {
+                    prefixed_list::max_bits(inner_type, element, *bits)
+                }
+                ListLength::Terminator(_) | ListLength::UntilLast => {
+                    terminated_list::max_bits()
+                }
+            },
+            Field::IsLast(_) => is_last::max_bits(),
             Field::Array {
-                inner_type, length, ..
-            } => array::max_bits(inner_type, length),
+                element, length, ..
+            } => array::max_bits(element, length),
         }
     }
 }
@@ -1,6 +1,8 @@
 use proc_macro2::{Literal, TokenStream};
-use quote::quote_spanned;
-use syn::spanned::Spanned;
+use quote::{quote, quote_spanned};
+
+use crate::codegen::shape;
+use crate::model::{NormalField, Shape, member_name};
 
 // TO-Do remove unsafe:
 // generate:
@@ -8,11 +10,19 @@ use syn::spanned::Spanned;
 pub(crate) fn read(
     length: &syn::Expr,
     ty: &syn::Type,
-    field: &syn::Field,
+    element: &Shape,
+    field: &NormalField,
     struct_name: &Literal,
 ) -> TokenStream {
     let field_ident = &field.ident;
-    let field_name = Literal::string(&field_ident.as_ref().map(|i| i.to_string()).unwrap_or_default());
+    let field_name = Literal::string(&member_name(&field.member));
+    if !matches!(element, Shape::Other(_)) {
+        let read_array = shape::read(&array_shape(element, length));
+        return quote_spanned! {field.ident.span()=>
+            let #field_ident = #read_array
+                .map_err(|cause| cause.read_array(#struct_name, #field_name, #length))?;
+        };
+    }
 
     quote_spanned! {field.ident.span()=>
         const LEN: usize = #length;
@@ -34,8 +44,8 @@ pub(crate) fn read(
                     // # SAFETY
                     // `array[0..i]` are initialized, we need to drop those elements
                     unsafe {
-                        for j in 0..i {
-                            array[j].assume_init_drop();
+                        for element in array.iter_mut().take(i) {
+                            element.assume_init_drop();
                         }
                     }
                     return Err(e.read_array(#struct_name, #field_name, LEN));
@@ -47,28 +57,43 @@ pub(crate) fn read(
         // The loop completed, every element is therefore initialized. In memory
         // arrays of `MaybeUninit<T>` look the same as `T`, therefore the transmute
         // is safe
-        let res = unsafe { ::core::mem::transmute::<_, [#ty; LEN]>(array) };
+        let res = unsafe {
+            ::core::mem::transmute::<[::core::mem::MaybeUninit<#ty>; LEN], [#ty; LEN]>(array)
+        };
         let #field_ident = res;
     }
 }
 
-pub(crate) fn write(field: &syn::Field) -> TokenStream {
+pub(crate) fn write(
+    element: &Shape,
+    length: &syn::Expr,
+    field: &NormalField,
+    struct_name: &Literal,
+) -> TokenStream {
     let field_ident = &field.ident;
+    let field_name = Literal::string(&member_name(&field.member));
+    let write_array = shape::write(
+        &array_shape(element, length),
+        quote! { #field_ident },
+        &field_name,
+        struct_name,
+    );
     quote_spanned! {field_ident.span()=>
-        for element in &self.#field_ident {
-            ::abstract_bits::AbstractBits::write_abstract_bits(element, writer)?;
-        }
+        #write_array
     }
 }
 
-pub(crate) fn min_bits(inner_type: &syn::Type, length: &syn::Expr) -> TokenStream {
-    quote_spanned! {inner_type.span()=>
-        <#inner_type as ::abstract_bits::AbstractBits>::MIN_BITS * #length
-    }
+pub(crate) fn min_bits(element: &Shape, length: &syn::Expr) -> TokenStream {
+    shape::min_bits(&array_shape(element, length))
+}
+
+pub(crate) fn max_bits(element: &Shape, length: &syn::Expr) -> TokenStream {
+    shape::max_bits(&array_shape(element, length))
 }
 
-pub(crate) fn max_bits(inner_type: &syn::Type, length: &syn::Expr) -> TokenStream {
-    quote_spanned! {inner_type.span()=>
-        <#inner_type as ::abstract_bits::AbstractBits>::MAX_BITS * #length
+fn array_shape(element: &Shape, length: &syn::Expr) -> Shape {
+    Shape::Array {
+        inner: Box::new(element.clone()),
+        length: length.clone(),
     }
 }
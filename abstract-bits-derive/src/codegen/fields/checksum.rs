@@ -0,0 +1,113 @@
+use std::ops::Range;
+
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+use crate::codegen::{checksum_ident, is_primitive, position_ident};
+
+/// Reads the stored checksum, it is checked by [`check`] once the covered
+/// fields are read too
+pub fn read(bits: usize, index: usize, struct_name: &Literal) -> TokenStream {
+    let ident = checksum_ident(index);
+    let value = match is_primitive(bits) {
+        Some(_) => quote! { #ident },
+        None => quote! { #ident.value() },
+    };
+    let ty = wire_type(bits);
+    quote! {
+        let #ident = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+            .map_err(|cause| cause.read_field(#struct_name, "checksum"))?;
+        let #ident = #value as u64;
+    }
+}
+
+/// Leaves room for the checksum, it is filled in by [`fill_in`]
+pub fn write(bits: usize, index: usize, struct_name: &Literal) -> TokenStream {
+    let ident = checksum_ident(index);
+    let skip = super::padding::write(bits as u8, struct_name);
+    quote! {
+        let #ident = writer.bits_written();
+        #skip
+    }
+}
+
+pub fn check(
+    algorithm: &syn::Type,
+    bits: usize,
+    index: usize,
+    covers: &Range<usize>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let ident = checksum_ident(index);
+    let compute = compute(algorithm, bits, covers, quote! { reader });
+    quote! {
+        {
+            let expected = #compute;
+            if expected != #ident {
+                return Err(::abstract_bits::FromBytesError::ChecksumMismatch {
+                    struct_name: #struct_name,
+                    expected,
+                    found: #ident,
+                });
+            }
+        }
+    }
+}
+
+pub fn fill_in(
+    algorithm: &syn::Type,
+    bits: usize,
+    index: usize,
+    covers: &Range<usize>,
+) -> TokenStream {
+    let ident = checksum_ident(index);
+    let compute = compute(algorithm, bits, covers, quote! { writer });
+    let ty = wire_type(bits);
+    let to_wire_type = match is_primitive(bits) {
+        Some(_) => quote! { checksum as #ty },
+        None => quote! { #ty::new(checksum as _) },
+    };
+    quote! {
+        {
+            let checksum = #compute;
+            writer.write_at(#ident, &(#to_wire_type))?;
+        }
+    }
+}
+
+/// Only the low bits that fit the field are kept
+fn compute(
+    algorithm: &syn::Type,
+    bits: usize,
+    covers: &Range<usize>,
+    buffer: TokenStream,
+) -> TokenStream {
+    let start = position_ident(covers.start);
+    let end = position_ident(covers.end);
+    let mask = Literal::u64_unsuffixed(u64::MAX >> (64 - bits));
+    quote! {
+        <#algorithm as ::abstract_bits::Checksum>::checksum(
+            &#buffer.bytes_in(#start..#end)
+        ) & #mask
+    }
+}
+
+fn wire_type(bits: usize) -> TokenStream {
+    if let Some(ty) = is_primitive(bits) {
+        ty
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype }
+    }
+}
+
+pub(crate) fn min_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
+
+pub(crate) fn max_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
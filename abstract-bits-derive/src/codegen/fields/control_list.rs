@@ -1,20 +1,79 @@
 use proc_macro2::{Literal, TokenStream};
 use quote::{quote, quote_spanned};
-use syn::Ident;
+use syn::Member;
+use syn::spanned::Spanned;
 
 use crate::codegen::{is_primitive, list_len_ident};
+use crate::model::{Kept, LengthTransform, OnWrite, binding, member_name};
 
-pub fn read(controlled: &Ident, bits: usize, struct_name: &Literal) -> TokenStream {
-    let list_name = Literal::string(&controlled.to_string());
-    let len_ident = list_len_ident(controlled);
+pub fn read(
+    controlled: &Member,
+    shared: &[Member],
+    bits: usize,
+    transform: Option<&LengthTransform>,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let len_ident = list_len_ident(&binding(controlled));
+    let shared_len_idents = shared.iter().map(|member| list_len_ident(&binding(member)));
+    let len_code = read_len(controlled, bits, transform, kept, struct_name);
+    quote_spanned! {controlled.span()=>
+        #len_code
+        #(let #shared_len_idents = #len_ident;)*
+    }
+}
+
+fn read_len(
+    controlled: &Member,
+    bits: usize,
+    transform: Option<&LengthTransform>,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let list_name = Literal::string(&member_name(controlled));
+    let len_ident = list_len_ident(&binding(controlled));
+    let raw_code = match kept {
+        Some(Kept { field, .. }) => {
+            let read_code = super::normal::read(field, struct_name);
+            let ident = &field.ident;
+            quote! {
+                #read_code
+                let #len_ident = #ident;
+            }
+        }
+        None => read_raw(controlled, bits, struct_name),
+    };
+    let Some(LengthTransform { decoded, uses, .. }) = transform else {
+        return raw_code;
+    };
+    // the expression sees earlier fields by reference just like when writing
+    quote_spanned! {decoded.span()=>
+        #raw_code
+        let #len_ident = {
+            #(let #uses = &#uses;)*
+            let raw = #len_ident as i128;
+            let len: Option<i128> = #decoded;
+            len.and_then(|len| usize::try_from(len).ok())
+                .ok_or(::abstract_bits::FromBytesError::InvalidListLength {
+                    field_name: #list_name,
+                    struct_name: #struct_name,
+                    raw: raw as u64,
+                })?
+        };
+    }
+}
+
+fn read_raw(controlled: &Member, bits: usize, struct_name: &Literal) -> TokenStream {
+    let list_name = Literal::string(&member_name(controlled));
+    let len_ident = list_len_ident(&binding(controlled));
     if let Some(ty) = is_primitive(bits) {
         quote_spanned! {controlled.span()=>
             let #len_ident = #ty::read_abstract_bits(reader)
                 .map_err(|cause| cause.read_list_length(#struct_name, #list_name))?;
         }
     } else {
-        let utype: syn::Type =
-            syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path");
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
         quote_spanned! {controlled.span()=>
             let #len_ident = #utype::read_abstract_bits(reader)
                 .map_err(|cause| cause.read_list_length(#struct_name, #list_name))?;
@@ -23,30 +82,150 @@ pub fn read(controlled: &Ident, bits: usize, struct_name: &Literal) -> TokenStre
     }
 }
 
-pub fn write(controlled: &Ident, bits: usize) -> TokenStream {
-    let len_ident = list_len_ident(controlled);
+pub fn write(
+    controlled: &Member,
+    shared: &[Member],
+    bits: usize,
+    transform: Option<&LengthTransform>,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let list_name = Literal::string(&member_name(controlled));
+    let list = binding(controlled);
+    let checks = shared.iter().map(|member| {
+        let other_name = Literal::string(&member_name(member));
+        let other = binding(member);
+        quote_spanned! {member.span()=>
+            if #other.len() != #list.len() {
+                return Err(::abstract_bits::ToBytesError::ListLengthMismatch {
+                    field_name: #list_name,
+                    other_field: #other_name,
+                    struct_name: #struct_name,
+                    len: #list.len(),
+                    other_len: #other.len(),
+                });
+            }
+        }
+    });
+    let len_code = match kept {
+        None => write_len(controlled, bits, transform, struct_name),
+        Some(Kept {
+            field,
+            on_write: OnWrite::Recompute,
+        }) => {
+            let ident = &field.ident;
+            let write_code = write_len(controlled, bits, transform, struct_name);
+            quote! {
+                let _ = #ident;
+                #write_code
+            }
+        }
+        Some(Kept {
+            field,
+            on_write: OnWrite::Check,
+        }) => {
+            let ident = &field.ident;
+            let field_name = Literal::string(&member_name(&field.member));
+            let matches = match transform {
+                None => quote! { *#ident as usize == #list.len() },
+                Some(LengthTransform { decoded, .. }) => quote! {
+                    {
+                        let raw = *#ident as i128;
+                        let len: Option<i128> = #decoded;
+                        len == Some(#list.len() as i128)
+                    }
+                },
+            };
+            let write_code = super::normal::write(field, struct_name);
+            quote_spanned! {ident.span()=>
+                if !#matches {
+                    return Err(::abstract_bits::ToBytesError::ControllerMismatch {
+                        field_name: #field_name,
+                        controlled: #list_name,
+                        struct_name: #struct_name,
+                    });
+                }
+                #write_code
+            }
+        }
+    };
+    quote_spanned! {controlled.span()=>
+        #(#checks)*
+        #len_code
+    }
+}
+
+fn write_len(
+    controlled: &Member,
+    bits: usize,
+    transform: Option<&LengthTransform>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let list_name = Literal::string(&member_name(controlled));
+    let len_ident = list_len_ident(&binding(controlled));
+    let controlled = binding(controlled);
+    if let Some(LengthTransform {
+        encoded, decoded, ..
+    }) = transform
+    {
+        let max_len = max_len(bits, decoded);
+        let to_wire_type = match is_primitive(bits) {
+            Some(ty) => quote! { encoded as #ty },
+            None => {
+                let utype: syn::Type =
+                    syn::parse_str(&format!("::abstract_bits::u{bits}"))
+                        .expect("valid type path");
+                quote! { #utype::new(encoded as _) }
+            }
+        };
+        let max_raw = Literal::i128_unsuffixed((u64::MAX >> (64 - bits)) as i128);
+        return quote_spanned! {encoded.span()=>
+            {
+                let len = #controlled.len() as i128;
+                let encoded: i128 = #encoded;
+                if encoded > #max_raw {
+                    return Err(::abstract_bits::ToBytesError::ListTooLong {
+                        max: #max_len,
+                        got: #controlled.len(),
+                    });
+                }
+                // lengths the stored value can not express exactly, such as
+                // those encoded below zero
+                let raw = encoded;
+                let decoded: Option<i128> = #decoded;
+                if encoded < 0 || decoded != Some(len) {
+                    return Err(::abstract_bits::ToBytesError::LengthNotEncodable {
+                        field_name: #list_name,
+                        struct_name: #struct_name,
+                        len: #controlled.len(),
+                    });
+                }
+                ::abstract_bits::AbstractBits::write_abstract_bits(&(#to_wire_type), writer)?;
+            }
+        };
+    }
     if let Some(ty) = is_primitive(bits) {
         quote_spanned! {controlled.span()=>
-            let #len_ident: #ty = self.#controlled.len().try_into()
+            let #len_ident: #ty = #controlled.len().try_into()
                 .map_err(|_| ::abstract_bits::ToBytesError::ListTooLong {
                     max: #ty::MAX as usize,
-                    got: self.#controlled.len(),
+                    got: #controlled.len(),
             })?;
             ::abstract_bits::AbstractBits::write_abstract_bits(&#len_ident, writer)?;
         }
     } else {
-        let utype: syn::Type =
-            syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path");
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
         quote_spanned! {controlled.span()=>
-            let #len_ident = self.#controlled.len().try_into()
+            let #len_ident = #controlled.len().try_into()
                 .map_err(|_| ::abstract_bits::ToBytesError::ListTooLong {
                     max: 2usize.pow(#utype::BITS as u32) - 1,
-                    got: self.#controlled.len(),
+                    got: #controlled.len(),
                 })?;
             let #len_ident = #utype::try_new(#len_ident)
                 .map_err(|_| ::abstract_bits::ToBytesError::ListTooLong {
                     max: 2usize.pow(#utype::BITS as u32) - 1,
-                    got: self.#controlled.len(),
+                    got: #controlled.len(),
                 })?;
             ::abstract_bits::AbstractBits::write_abstract_bits(&#len_ident, writer)?;
         }
@@ -66,3 +245,29 @@ pub(crate) fn max_bits(n_bits: usize) -> TokenStream {
         #n_bits
     }
 }
+
+/// Longest list the controller can describe, the length has to grow or
+/// shrink with the stored value
+pub(crate) fn max_len(bits: usize, decoded: &syn::Expr) -> TokenStream {
+    let max_raw = Literal::i128_unsuffixed((u64::MAX >> (64 - bits)) as i128);
+    quote_spanned! {decoded.span()=>
+        {
+            let smallest = match { let raw: i128 = 0; #decoded } {
+                Some(len) => len,
+                None => 0,
+            };
+            let largest = match { let raw: i128 = #max_raw; #decoded } {
+                Some(len) => len,
+                None => 0,
+            };
+            let max: i128 = if smallest > largest { smallest } else { largest };
+            if max < 0 {
+                0
+            } else if max > usize::MAX as i128 {
+                usize::MAX
+            } else {
+                max as usize
+            }
+        }
+    }
+}
@@ -1,30 +1,172 @@
 use proc_macro2::{Literal, TokenStream};
 use quote::{quote, quote_spanned};
-use syn::Ident;
+use syn::Member;
+use syn::spanned::Spanned;
 
-pub fn read(controlled: &Ident, struct_name: &Literal) -> TokenStream {
-    let option_controlled = Literal::string(&controlled.to_string());
-    let controller_ident = super::option::is_some_ident(controlled);
+use crate::codegen::is_primitive;
+use crate::model::{Kept, OnWrite, binding, member_name};
+
+pub fn read(
+    controlled: &Member,
+    bits: usize,
+    present: &[u64],
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let option_controlled = Literal::string(&member_name(controlled));
+    let controller_ident = super::option::is_some_ident(&binding(controlled));
+    if let Some(Kept { field, .. }) = kept {
+        let read_code = super::normal::read(field, struct_name);
+        let ident = &field.ident;
+        let is_some = is_some(&controller_ident, quote! { #ident }, bits, present);
+        return quote_spanned! {controlled.span()=>
+            #read_code
+            #is_some
+        };
+    }
+    let ty = wire_type(bits);
+    let is_some = if bits == 1 && present == [1] {
+        quote! {}
+    } else if bits == 1 || is_primitive(bits).is_some() {
+        is_some(
+            &controller_ident,
+            quote! { #controller_ident },
+            bits,
+            present,
+        )
+    } else {
+        is_some(
+            &controller_ident,
+            quote! { #controller_ident.value() },
+            bits,
+            present,
+        )
+    };
     quote_spanned! {controlled.span()=>
-        let #controller_ident = bool::read_abstract_bits(reader)
+        let #controller_ident = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
             .map_err(|cause| cause.read_option_controller(#struct_name, #option_controlled))?;
+        #is_some
     }
 }
 
-pub fn write(controlled: &Ident) -> TokenStream {
-    quote_spanned! {controlled.span()=>
-        if self.#controlled.is_some() {
-            true.write_abstract_bits(writer)?;
+/// Binds whether the option is present given the controller's `value`
+fn is_some(
+    controller_ident: &syn::Ident,
+    value: TokenStream,
+    bits: usize,
+    present: &[u64],
+) -> TokenStream {
+    if bits == 1 {
+        // a bool can only be present when set or present when unset
+        if present == [1] {
+            quote! { let #controller_ident = #value; }
         } else {
-            false.write_abstract_bits(writer)?;
+            quote! { let #controller_ident = !#value; }
+        }
+    } else {
+        let present = present.iter().map(|v| Literal::u64_unsuffixed(*v));
+        quote! { let #controller_ident = matches!(#value, #(#present)|*); }
+    }
+}
+
+pub fn write(
+    controlled: &Member,
+    bits: usize,
+    present: &[u64],
+    absent: u64,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    match kept {
+        None => write_controller(controlled, bits, present[0], absent),
+        Some(Kept {
+            field,
+            on_write: OnWrite::Recompute,
+        }) => {
+            let ident = &field.ident;
+            let write_code = write_controller(controlled, bits, present[0], absent);
+            quote! {
+                let _ = #ident;
+                #write_code
+            }
+        }
+        Some(Kept {
+            field,
+            on_write: OnWrite::Check,
+        }) => {
+            let option_controlled = Literal::string(&member_name(controlled));
+            let controller_ident = super::option::is_some_ident(&binding(controlled));
+            let controlled = binding(controlled);
+            let ident = &field.ident;
+            let field_name = Literal::string(&member_name(&field.member));
+            let is_some = is_some(&controller_ident, quote! { *#ident }, bits, present);
+            let write_code = super::normal::write(field, struct_name);
+            quote_spanned! {ident.span()=>
+                {
+                    #is_some
+                    if #controller_ident != #controlled.is_some() {
+                        return Err(::abstract_bits::ToBytesError::ControllerMismatch {
+                            field_name: #field_name,
+                            controlled: #option_controlled,
+                            struct_name: #struct_name,
+                        });
+                    }
+                }
+                #write_code
+            }
         }
     }
 }
 
-pub(crate) fn min_bits() -> TokenStream {
-    quote! { 1 }
+fn write_controller(
+    controlled: &Member,
+    bits: usize,
+    present: u64,
+    absent: u64,
+) -> TokenStream {
+    let controlled = binding(controlled);
+    let ty = wire_type(bits);
+    let inverted = absent == 1;
+    let (present, absent) = (
+        Literal::u64_unsuffixed(present),
+        Literal::u64_unsuffixed(absent),
+    );
+    let value = if bits == 1 && !inverted {
+        quote! { #controlled.is_some() }
+    } else if bits == 1 {
+        quote! { #controlled.is_none() }
+    } else if is_primitive(bits).is_some() {
+        quote! { if #controlled.is_some() { #present } else { #absent } }
+    } else {
+        quote! { if #controlled.is_some() { #ty::new(#present) } else { #ty::new(#absent) } }
+    };
+    quote_spanned! {controlled.span()=>
+        {
+            let value: #ty = #value;
+            ::abstract_bits::AbstractBits::write_abstract_bits(&value, writer)?;
+        }
+    }
+}
+
+/// A one bit controller is a `bool`
+fn wire_type(bits: usize) -> TokenStream {
+    if bits == 1 {
+        quote! { bool }
+    } else if let Some(ty) = is_primitive(bits) {
+        ty
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype }
+    }
+}
+
+pub(crate) fn min_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
 }
 
-pub(crate) fn max_bits() -> TokenStream {
-    quote! { 1 }
+pub(crate) fn max_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
 }
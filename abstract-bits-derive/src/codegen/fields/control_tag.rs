@@ -0,0 +1,121 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::Member;
+use syn::spanned::Spanned;
+
+use crate::codegen::{is_primitive, tag_ident};
+use crate::model::{Kept, OnWrite, binding, member_name};
+
+pub fn read(
+    controlled: &Member,
+    bits: usize,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    let enum_name = Literal::string(&member_name(controlled));
+    let tag_ident = tag_ident(&binding(controlled));
+    if let Some(Kept { field, .. }) = kept {
+        let read_code = super::normal::read(field, struct_name);
+        let ident = &field.ident;
+        quote_spanned! {controlled.span()=>
+            #read_code
+            let #tag_ident = #ident as usize;
+        }
+    } else if let Some(ty) = is_primitive(bits) {
+        quote_spanned! {controlled.span()=>
+            let #tag_ident = #ty::read_abstract_bits(reader)
+                .map_err(|cause| cause.read_tag(#struct_name, #enum_name))?;
+            let #tag_ident = #tag_ident as usize;
+        }
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote_spanned! {controlled.span()=>
+            let #tag_ident = #utype::read_abstract_bits(reader)
+                .map_err(|cause| cause.read_tag(#struct_name, #enum_name))?;
+            let #tag_ident = #tag_ident.value() as usize;
+        }
+    }
+}
+
+pub fn write(
+    controlled: &Member,
+    bits: usize,
+    kept: Option<&Kept>,
+    struct_name: &Literal,
+) -> TokenStream {
+    match kept {
+        None => write_tag(controlled, bits, struct_name),
+        Some(Kept {
+            field,
+            on_write: OnWrite::Recompute,
+        }) => {
+            let ident = &field.ident;
+            let write_code = write_tag(controlled, bits, struct_name);
+            quote! {
+                let _ = #ident;
+                #write_code
+            }
+        }
+        Some(Kept {
+            field,
+            on_write: OnWrite::Check,
+        }) => {
+            let enum_name = Literal::string(&member_name(controlled));
+            let controlled = binding(controlled);
+            let ident = &field.ident;
+            let field_name = Literal::string(&member_name(&field.member));
+            let write_code = super::normal::write(field, struct_name);
+            quote_spanned! {ident.span()=>
+                if *#ident as usize != ::abstract_bits::Tagged::tag(#controlled) {
+                    return Err(::abstract_bits::ToBytesError::ControllerMismatch {
+                        field_name: #field_name,
+                        controlled: #enum_name,
+                        struct_name: #struct_name,
+                    });
+                }
+                #write_code
+            }
+        }
+    }
+}
+
+fn write_tag(controlled: &Member, bits: usize, struct_name: &Literal) -> TokenStream {
+    let enum_name = Literal::string(&member_name(controlled));
+    let tag_ident = tag_ident(&binding(controlled));
+    let controlled = binding(controlled);
+    let max = Literal::u64_suffixed(u64::MAX >> (64 - bits));
+    let to_wire_type = if let Some(ty) = is_primitive(bits) {
+        quote! { #tag_ident as #ty }
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype::new(#tag_ident as _) }
+    };
+    quote_spanned! {controlled.span()=>
+        let #tag_ident = ::abstract_bits::Tagged::tag(#controlled);
+        if #tag_ident as u64 > #max {
+            return Err(::abstract_bits::ToBytesError::ValueTooLarge {
+                field_name: #enum_name,
+                struct_name: #struct_name,
+                max: #max,
+                got: #tag_ident as u64,
+            });
+        }
+        ::abstract_bits::AbstractBits::write_abstract_bits(&(#to_wire_type), writer)?;
+    }
+}
+
+pub(crate) fn min_bits(n_bits: usize) -> TokenStream {
+    let n_bits = proc_macro2::Literal::usize_unsuffixed(n_bits);
+    quote! {
+        #n_bits
+    }
+}
+
+pub(crate) fn max_bits(n_bits: usize) -> TokenStream {
+    let n_bits = proc_macro2::Literal::usize_unsuffixed(n_bits);
+    quote! {
+        #n_bits
+    }
+}
@@ -0,0 +1,31 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::Member;
+use syn::spanned::Spanned;
+
+use crate::codegen::is_last_ident;
+use crate::model::member_name;
+
+pub fn read(member: &Member, struct_name: &Literal) -> TokenStream {
+    let field_name = Literal::string(&member_name(member));
+    let is_last = is_last_ident();
+    quote_spanned! {member.span()=>
+        let #is_last = <bool as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+            .map_err(|cause| cause.read_field(#struct_name, #field_name))?;
+    }
+}
+
+pub fn write(member: &Member) -> TokenStream {
+    let is_last = is_last_ident();
+    quote_spanned! {member.span()=>
+        ::abstract_bits::AbstractBits::write_abstract_bits(&#is_last, writer)?;
+    }
+}
+
+pub(crate) fn min_bits() -> TokenStream {
+    quote! { 1 }
+}
+
+pub(crate) fn max_bits() -> TokenStream {
+    quote! { 1 }
+}
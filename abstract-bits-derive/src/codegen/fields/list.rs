@@ -1,28 +1,40 @@
 use proc_macro2::{Literal, TokenStream};
-use quote::quote_spanned;
+use quote::{quote, quote_spanned};
 
-use crate::codegen::list_len_ident;
-use crate::model::NormalField;
+use crate::codegen::{list_len_ident, shape};
+use crate::model::{LengthTransform, NormalField, Shape, member_name};
 
-pub(crate) fn write(inner_type: &NormalField) -> TokenStream {
+pub(crate) fn write(
+    inner_type: &NormalField,
+    element: &Shape,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&inner_type.member));
     let field_ident = &inner_type.ident;
+    let write_element =
+        shape::write(element, quote! { element }, &field_name, struct_name);
     quote_spanned! {field_ident.span()=>
-        for element in &self.#field_ident {
-            ::abstract_bits::AbstractBits::write_abstract_bits(element, writer)?;
+        for element in #field_ident {
+            #write_element
         }
     }
 }
 
-pub(crate) fn read(field: &NormalField, struct_name: &Literal) -> TokenStream {
-    let field_name = Literal::string(&field.ident.to_string());
+pub(crate) fn read(
+    field: &NormalField,
+    element: &Shape,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&field.member));
     let len_ident = list_len_ident(&field.ident);
     let field_ident = &field.ident;
+    let read_element = shape::read(element);
     quote_spanned! {field.ident.span()=>
-        let res = (0..#len_ident).into_iter().map(|_|
-            ::abstract_bits::AbstractBits::read_abstract_bits(reader)
+        let res = (0..#len_ident).map(|_|
+            #read_element
         )
             .collect::<Result<_, ::abstract_bits::FromBytesError>>()
-            .map_err(|cause| cause.read_list(#struct_name, 
+            .map_err(|cause| cause.read_list(#struct_name,
                     #field_name, #len_ident as usize));
         let #field_ident = res?;
     }
@@ -34,9 +46,25 @@ pub(crate) fn min_bits(inner_type: &NormalField) -> TokenStream {
     }
 }
 
-pub(crate) fn max_bits(inner_type: &NormalField, max_len: usize) -> TokenStream {
-    let ty = &inner_type.out_ty;
+pub(crate) fn max_bits(
+    inner_type: &NormalField,
+    element: &Shape,
+    bits: usize,
+    transform: Option<&LengthTransform>,
+) -> TokenStream {
+    let max_len = match transform {
+        None => {
+            let max_len = 2usize.pow(bits as u32);
+            quote! { #max_len }
+        }
+        Some(LengthTransform { decoded, uses, .. }) if uses.is_empty() => {
+            super::control_list::max_len(bits, decoded)
+        }
+        // depends on values only known when writing
+        Some(_) => return quote! { usize::MAX },
+    };
+    let element_bits = shape::max_bits(element);
     quote_spanned! {inner_type.ident.span()=>
-        #max_len * <#ty as ::abstract_bits::AbstractBits>::MAX_BITS
+        usize::saturating_mul(#max_len, #element_bits)
     }
 }
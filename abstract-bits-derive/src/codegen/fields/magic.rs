@@ -0,0 +1,62 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+use crate::codegen::is_primitive;
+
+pub fn read(bits: usize, value: u64, struct_name: &Literal) -> TokenStream {
+    let expected = Literal::u64_unsuffixed(value);
+    let ty = wire_type(bits);
+    let found = match is_primitive(bits) {
+        Some(_) => quote! { magic },
+        None => quote! { magic.value() },
+    };
+    quote! {
+        {
+            let magic = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+                .map_err(|cause| cause.read_field(#struct_name, "magic"))?;
+            let found = #found as u64;
+            if found != #expected {
+                return Err(::abstract_bits::FromBytesError::BadMagic {
+                    struct_name: #struct_name,
+                    expected: #expected,
+                    found,
+                });
+            }
+        }
+    }
+}
+
+pub fn write(bits: usize, value: u64) -> TokenStream {
+    let value = Literal::u64_unsuffixed(value);
+    let ty = wire_type(bits);
+    let magic = match is_primitive(bits) {
+        Some(_) => quote! { #value },
+        None => quote! { #ty::new(#value) },
+    };
+    quote! {
+        {
+            let magic: #ty = #magic;
+            ::abstract_bits::AbstractBits::write_abstract_bits(&magic, writer)?;
+        }
+    }
+}
+
+fn wire_type(bits: usize) -> TokenStream {
+    if let Some(ty) = is_primitive(bits) {
+        ty
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype }
+    }
+}
+
+pub(crate) fn min_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
+
+pub(crate) fn max_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
@@ -1,13 +1,14 @@
 use proc_macro2::Literal;
 use proc_macro2::TokenStream;
-use quote::{ToTokens, quote_spanned};
+use quote::{ToTokens, quote, quote_spanned};
 use syn::spanned::Spanned;
 
-use crate::codegen::generics_to_fully_qualified;
-use crate::model::NormalField;
+use crate::codegen::shape;
+use crate::model::{NormalField, Shape, member_name};
 
 pub fn read(
     NormalField {
+        member,
         ident,
         out_ty,
         bits,
@@ -15,7 +16,7 @@ pub fn read(
     }: &NormalField,
     struct_name: &Literal,
 ) -> TokenStream {
-    let field_name = proc_macro2::Literal::string(&ident.to_string());
+    let field_name = proc_macro2::Literal::string(&member_name(member));
     if let Some(bits) = bits {
         let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
             .expect("should be valid type path");
@@ -25,9 +26,8 @@ pub fn read(
             let #ident = #ident.value();
         }
     } else {
-        let out_ty = generics_to_fully_qualified(out_ty.clone());
         quote_spanned! {out_ty.span()=>
-            let #ident = #out_ty::read_abstract_bits(reader)
+            let #ident = <#out_ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
                 .map_err(|cause| cause.read_field(#struct_name, #field_name))?;
         }
     }
@@ -35,22 +35,28 @@ pub fn read(
 
 pub fn write(
     NormalField {
+        member,
         ident,
         out_ty,
         bits,
         ..
     }: &NormalField,
+    struct_name: &Literal,
 ) -> TokenStream {
     if let Some(bits) = *bits {
-        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
-            .expect("should be valid type path");
+        let field_name = Literal::string(&member_name(member));
+        let write_code = shape::write(
+            &Shape::UInt(bits),
+            quote! { #ident },
+            &field_name,
+            struct_name,
+        );
         quote_spanned! {out_ty.span()=>
-            let #ident = #utype::new(self.#ident);
-            #ident.write_abstract_bits(writer)?;
+            #write_code
         }
     } else {
         quote_spanned! {out_ty.span()=>
-            self.#ident.write_abstract_bits(writer)?;
+            #ident.write_abstract_bits(writer)?;
         }
     }
 }
@@ -61,7 +67,7 @@ pub(crate) fn min_bits(normal_field: &crate::model::NormalField) -> TokenStream
         proc_macro2::Literal::usize_unsuffixed(n as usize).to_token_stream()
     } else {
         quote_spanned! {normal_field.ident.span()=>
-            #ty::MIN_BITS
+            <#ty as ::abstract_bits::AbstractBits>::MIN_BITS
         }
     }
 }
@@ -72,7 +78,7 @@ pub(crate) fn max_bits(normal_field: &crate::model::NormalField) -> TokenStream
         proc_macro2::Literal::usize_unsuffixed(n as usize).to_token_stream()
     } else {
         quote_spanned! {normal_field.ident.span()=>
-                #ty::MAX_BITS
+                <#ty as ::abstract_bits::AbstractBits>::MAX_BITS
         }
     }
 }
@@ -1,10 +1,10 @@
 use proc_macro2::{Literal, TokenStream};
-use quote::{format_ident, quote_spanned};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
 use syn::Ident;
 use syn::spanned::Spanned;
 
-use crate::codegen::generics_to_fully_qualified;
-use crate::model::NormalField;
+use crate::codegen::shape;
+use crate::model::{NormalField, Presence, Shape, member_name};
 
 pub fn is_some_ident(controlled: &Ident) -> Ident {
     format_ident!("{controlled}_is_some")
@@ -12,6 +12,7 @@ pub fn is_some_ident(controlled: &Ident) -> Ident {
 
 pub fn read_field_code(
     NormalField {
+        member,
         ident,
         out_ty,
         bits,
@@ -19,7 +20,7 @@ pub fn read_field_code(
     }: &NormalField,
     struct_name: &Literal,
 ) -> TokenStream {
-    let field_name = proc_macro2::Literal::string(&ident.to_string());
+    let field_name = proc_macro2::Literal::string(&member_name(member));
     if let Some(bits) = bits {
         let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
             .expect("should be valid type path");
@@ -29,19 +30,68 @@ pub fn read_field_code(
             let #ident = #ident.value();
         }
     } else {
-        let out_ty = generics_to_fully_qualified(out_ty.clone());
         quote_spanned! {out_ty.span()=>
-            let #ident = #out_ty::read_abstract_bits(reader)
+            let #ident = <#out_ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
                 .map_err(|cause| cause.read_option(#struct_name, #field_name))?;
         }
     }
 }
 
-pub fn read(field: &NormalField, struct_name: &Literal) -> TokenStream {
+pub fn read(
+    field: &NormalField,
+    presence: &Presence,
+    struct_name: &Literal,
+) -> TokenStream {
     let is_some_ident = is_some_ident(&field.ident);
     let field_ident = &field.ident;
     let field_read_code = read_field_code(field, struct_name);
+    // the condition sees earlier fields by reference just like when writing
+    let condition = match presence {
+        Presence::Controlled => quote! {},
+        Presence::NoneValue(none_value) => {
+            return quote_spanned! {field.ident.span()=>
+                #field_read_code
+                let #field_ident = if #field_ident == (#none_value) {
+                    None
+                } else {
+                    Some(#field_ident)
+                };
+            };
+        }
+        Presence::IfRemaining { .. } => {
+            let out_ty = &field.out_ty;
+            let min_bits = max_bits(field);
+            // fields of known size are checked when parsing
+            let (min_bits, at_least_a_byte) = if field.bits.is_some() {
+                (min_bits, quote! {})
+            } else {
+                let min_bits =
+                    quote! { <#out_ty as ::abstract_bits::AbstractBits>::MIN_BITS };
+                let check = quote_spanned! {out_ty.span()=>
+                    const {
+                        assert!(
+                            #min_bits >= 8,
+                            "an `if_remaining` field must take up at least a byte"
+                        )
+                    };
+                };
+                (min_bits, check)
+            };
+            quote_spanned! {field.ident.span()=>
+                #at_least_a_byte
+                let #is_some_ident = reader.bits_remaining() > 0
+                    && reader.bits_remaining() >= #min_bits;
+            }
+        }
+        Presence::Condition { expr, uses } => quote_spanned! {expr.span()=>
+            let #is_some_ident: bool = {
+                #(let #uses = &#uses;)*
+                #expr
+            };
+        },
+    };
     quote_spanned! {field.ident.span()=>
+        #condition
         let #field_ident = if #is_some_ident {
             #field_read_code
             Some(#field_ident)
@@ -51,38 +101,109 @@ pub fn read(field: &NormalField, struct_name: &Literal) -> TokenStream {
     }
 }
 
-pub fn write(field: &NormalField) -> TokenStream {
+pub fn write(
+    field: &NormalField,
+    presence: &Presence,
+    struct_name: &Literal,
+) -> TokenStream {
     let field_ident = &field.ident;
-    let write_code = if let Some(bits) = field.bits {
-        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
-            .expect("should be valid type path");
-        quote_spanned! {field.out_ty.span()=>
-            let #field_ident = #utype::new(#field_ident);
-            #field_ident.write_abstract_bits(writer)?;
+    let check_condition = match presence {
+        Presence::Controlled | Presence::NoneValue(_) => quote! {},
+        // once one is missing the rest of the input is gone
+        Presence::IfRemaining { after: Some(after) } => {
+            let field_name = Literal::string(&member_name(&field.member));
+            quote_spanned! {field_ident.span()=>
+                if #field_ident.is_some() && #after.is_none() {
+                    return Err(::abstract_bits::ToBytesError::PresenceMismatch {
+                        field_name: #field_name,
+                        struct_name: #struct_name,
+                        expected_some: false,
+                    });
+                }
+            }
+        }
+        Presence::IfRemaining { after: None } => quote! {},
+        Presence::Condition { expr, .. } => {
+            let field_name = Literal::string(&member_name(&field.member));
+            quote_spanned! {expr.span()=>
+                {
+                    let expected_some: bool = #expr;
+                    if #field_ident.is_some() != expected_some {
+                        return Err(::abstract_bits::ToBytesError::PresenceMismatch {
+                            field_name: #field_name,
+                            struct_name: #struct_name,
+                            expected_some,
+                        });
+                    }
+                }
+            }
         }
+    };
+    let write_code = if let Some(bits) = field.bits {
+        let field_name = Literal::string(&member_name(&field.member));
+        shape::write(
+            &Shape::UInt(bits),
+            quote! { #field_ident },
+            &field_name,
+            struct_name,
+        )
     } else {
         quote_spanned! {field.out_ty.span()=>
             #field_ident.write_abstract_bits(writer)?;
         }
     };
 
+    if let Presence::NoneValue(none_value) = presence {
+        let field_name = Literal::string(&member_name(&field.member));
+        let out_ty = &field.out_ty;
+        return quote_spanned!(field_ident.span()=>
+            match #field_ident {
+                Some(#field_ident) if *#field_ident == (#none_value) => {
+                    return Err(::abstract_bits::ToBytesError::ReservedNoneValue {
+                        field_name: #field_name,
+                        struct_name: #struct_name,
+                    });
+                }
+                Some(#field_ident) => {
+                    #write_code
+                }
+                None => {
+                    let #field_ident: &#out_ty = &(#none_value);
+                    #write_code
+                }
+            }
+        );
+    }
+
     quote_spanned!(field_ident.span()=>
-        if let Some(ref #field_ident) = self.#field_ident {
+        #check_condition
+        if let Some(#field_ident) = #field_ident {
             #write_code
         }
     )
 }
 
-pub(crate) fn min_bits(inner_type: &NormalField) -> TokenStream {
-    let ty = &inner_type.out_ty;
-    quote_spanned! {inner_type.ident.span()=>
-        #ty::MIN_BITS
+pub(crate) fn min_bits(inner_type: &NormalField, presence: &Presence) -> TokenStream {
+    match presence {
+        // the none value takes up as much space as any other
+        Presence::NoneValue(_) => max_bits(inner_type),
+        Presence::Controlled
+        | Presence::Condition { .. }
+        | Presence::IfRemaining { .. } => {
+            quote_spanned! {inner_type.ident.span()=>
+                0
+            }
+        }
     }
 }
 
 pub(crate) fn max_bits(inner_type: &NormalField) -> TokenStream {
     let ty = &inner_type.out_ty;
-    quote_spanned! {inner_type.ident.span()=>
-        #ty::MAX_BITS
+    if let Some(bits) = inner_type.bits {
+        Literal::usize_unsuffixed(bits as usize).to_token_stream()
+    } else {
+        quote_spanned! {inner_type.ident.span()=>
+            <#ty as ::abstract_bits::AbstractBits>::MAX_BITS
+        }
     }
 }
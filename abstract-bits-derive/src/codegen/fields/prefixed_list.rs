@@ -0,0 +1,88 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+
+use crate::codegen::{is_primitive, shape};
+use crate::model::{NormalField, Shape, member_name};
+
+pub(crate) fn write(
+    inner_type: &NormalField,
+    element: &Shape,
+    bits: usize,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&inner_type.member));
+    let field_ident = &inner_type.ident;
+    let prefix = prefix_type(bits);
+    let write_element =
+        shape::write(element, quote! { element }, &field_name, struct_name);
+    quote_spanned! {field_ident.span()=>
+        {
+            let len = <#prefix as ::abstract_bits::LengthPrefix>::from_len(#field_ident.len())
+                .ok_or(::abstract_bits::ToBytesError::ListTooLong {
+                    max: <#prefix as ::abstract_bits::LengthPrefix>::MAX_LEN,
+                    got: #field_ident.len(),
+                })?;
+            ::abstract_bits::AbstractBits::write_abstract_bits(&len, writer)?;
+            for element in #field_ident {
+                #write_element
+            }
+        }
+    }
+}
+
+pub(crate) fn read(
+    field: &NormalField,
+    element: &Shape,
+    bits: usize,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&field.member));
+    let field_ident = &field.ident;
+    let prefix = prefix_type(bits);
+    let read_element = shape::read(element);
+    quote_spanned! {field_ident.span()=>
+        let #field_ident = {
+            let len = <#prefix as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+                .map_err(|cause| cause.read_list_length(#struct_name, #field_name))?;
+            let len = ::abstract_bits::LengthPrefix::to_len(&len);
+            (0..len).map(|_|
+                #read_element
+            )
+                .collect::<Result<_, ::abstract_bits::FromBytesError>>()
+                .map_err(|cause| cause.read_list(#struct_name, #field_name, len))?
+        };
+    }
+}
+
+pub(crate) fn min_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
+
+pub(crate) fn max_bits(
+    inner_type: &NormalField,
+    element: &Shape,
+    bits: usize,
+) -> TokenStream {
+    let element_bits = shape::max_bits(element);
+    let prefix = prefix_type(bits);
+    quote_spanned! {inner_type.ident.span()=>
+        usize::saturating_add(
+            #bits,
+            usize::saturating_mul(
+                <#prefix as ::abstract_bits::LengthPrefix>::MAX_LEN,
+                #element_bits,
+            ),
+        )
+    }
+}
+
+fn prefix_type(bits: usize) -> TokenStream {
+    if let Some(ty) = is_primitive(bits) {
+        ty
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype }
+    }
+}
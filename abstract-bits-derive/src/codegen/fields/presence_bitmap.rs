@@ -0,0 +1,78 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::Member;
+use syn::spanned::Spanned;
+
+use crate::codegen::is_primitive;
+use crate::model::{binding, member_name};
+
+/// Bit `i` of the bitmap is set when the `i`-th listed option is present,
+/// unlisted bits are ignored
+pub fn read(controlled: &[Member], bits: usize, struct_name: &Literal) -> TokenStream {
+    let names = Literal::string(&option_names(controlled));
+    let (ty, value) = match is_primitive(bits) {
+        Some(ty) => (ty, quote! { bitmap }),
+        None => (utype(bits), quote! { bitmap.value() }),
+    };
+    let is_some = controlled.iter().enumerate().map(|(i, member)| {
+        let is_some_ident = super::option::is_some_ident(&binding(member));
+        let mask = Literal::u64_unsuffixed(1 << i);
+        quote_spanned! {member.span()=>
+            let #is_some_ident = (bitmap & #mask) != 0;
+        }
+    });
+    quote! {
+        let bitmap = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+            .map_err(|cause| cause.read_option_controller(#struct_name, #names))?;
+        let bitmap = #value;
+        #(#is_some)*
+    }
+}
+
+/// Unlisted bits are written as zero
+pub fn write(controlled: &[Member], bits: usize) -> TokenStream {
+    let flags = controlled.iter().enumerate().map(|(i, member)| {
+        let controlled = binding(member);
+        let mask = Literal::u64_unsuffixed(1 << i);
+        quote_spanned! {member.span()=>
+            if #controlled.is_some() { #mask } else { 0 }
+        }
+    });
+    let to_wire_type = match is_primitive(bits) {
+        Some(ty) => quote! { bitmap as #ty },
+        None => {
+            let utype = utype(bits);
+            quote! { #utype::new(bitmap as _) }
+        }
+    };
+    quote! {
+        {
+            let bitmap: u64 = #(#flags)|*;
+            ::abstract_bits::AbstractBits::write_abstract_bits(&(#to_wire_type), writer)?;
+        }
+    }
+}
+
+fn option_names(controlled: &[Member]) -> String {
+    controlled
+        .iter()
+        .map(member_name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn utype(bits: usize) -> TokenStream {
+    let utype: syn::Type =
+        syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path");
+    quote! { #utype }
+}
+
+pub(crate) fn min_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
+
+pub(crate) fn max_bits(bits: usize) -> TokenStream {
+    let bits = Literal::usize_unsuffixed(bits);
+    quote! { #bits }
+}
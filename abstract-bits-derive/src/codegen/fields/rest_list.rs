@@ -0,0 +1,49 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+
+use crate::codegen::shape;
+use crate::model::{NormalField, Shape, member_name};
+
+pub(crate) fn read(
+    full_type: &NormalField,
+    element: &Shape,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&full_type.member));
+    let field_ident = &full_type.ident;
+    let list_ty = &full_type.out_ty;
+    let min_bits = shape::min_bits(element);
+    let read_element = shape::read(element);
+    // elements of known size are checked when parsing
+    quote_spanned! {field_ident.span()=>
+        const {
+            assert!(
+                #min_bits >= 8,
+                "the elements of a `rest` list must take up at least a byte"
+            )
+        };
+        let mut #field_ident: #list_ty = Default::default();
+        while reader.bits_remaining() > 0 && reader.bits_remaining() >= #min_bits {
+            let bits_read = reader.bits_read();
+            let element = #read_element
+                .map_err(|cause| cause.read_list(#struct_name, #field_name,
+                    #field_ident.len()))?;
+            #field_ident.push(element);
+            if reader.bits_read() == bits_read {
+                break;
+            }
+        }
+        // anything left must be the padding of the last byte
+        if reader.bits_remaining() >= 8 {
+            return Err(::abstract_bits::FromBytesError::LeftoverBits {
+                field_name: #field_name,
+                struct_name: #struct_name,
+                n_bits: reader.bits_remaining(),
+            });
+        }
+    }
+}
+
+pub(crate) fn max_bits() -> TokenStream {
+    quote! { usize::MAX }
+}
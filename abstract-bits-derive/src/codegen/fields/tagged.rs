@@ -0,0 +1,42 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+use crate::codegen::tag_ident;
+use crate::model::{NormalField, member_name};
+
+pub fn read(field: &NormalField, struct_name: &Literal) -> TokenStream {
+    let NormalField {
+        member,
+        ident,
+        out_ty,
+        ..
+    } = field;
+    let field_name = Literal::string(&member_name(member));
+    let tag_ident = tag_ident(ident);
+    quote_spanned! {out_ty.span()=>
+        let #ident = <#out_ty as ::abstract_bits::Tagged>::read_body(#tag_ident, reader)
+            .map_err(|cause| cause.read_field(#struct_name, #field_name))?;
+    }
+}
+
+pub fn write(field: &NormalField) -> TokenStream {
+    let NormalField { ident, out_ty, .. } = field;
+    quote_spanned! {out_ty.span()=>
+        ::abstract_bits::Tagged::write_body(#ident, writer)?;
+    }
+}
+
+pub(crate) fn min_bits(field: &NormalField) -> TokenStream {
+    let ty = &field.out_ty;
+    quote_spanned! {field.ident.span()=>
+        <#ty as ::abstract_bits::Tagged>::MIN_BODY_BITS
+    }
+}
+
+pub(crate) fn max_bits(field: &NormalField) -> TokenStream {
+    let ty = &field.out_ty;
+    quote_spanned! {field.ident.span()=>
+        <#ty as ::abstract_bits::Tagged>::MAX_BODY_BITS
+    }
+}
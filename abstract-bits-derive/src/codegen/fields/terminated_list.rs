@@ -0,0 +1,117 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+
+use crate::codegen::shape;
+use crate::model::{NormalField, Shape, member_name};
+
+pub(crate) fn write_terminator(
+    inner_type: &NormalField,
+    element: &Shape,
+    terminator: &syn::Expr,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&inner_type.member));
+    let field_ident = &inner_type.ident;
+    let ty = &inner_type.out_ty;
+    let write_element =
+        shape::write(element, quote! { element }, &field_name, struct_name);
+    let write_terminator =
+        shape::write(element, quote! { &terminator }, &field_name, struct_name);
+    quote_spanned! {field_ident.span()=>
+        {
+            let terminator: #ty = #terminator;
+            for element in #field_ident {
+                // reading would stop early at this element
+                if *element == terminator {
+                    return Err(::abstract_bits::ToBytesError::TerminatorInList {
+                        field_name: #field_name,
+                        struct_name: #struct_name,
+                    });
+                }
+                #write_element
+            }
+            #write_terminator
+        }
+    }
+}
+
+pub(crate) fn read_terminator(
+    full_type: &NormalField,
+    inner_type: &NormalField,
+    element: &Shape,
+    terminator: &syn::Expr,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&full_type.member));
+    let field_ident = &full_type.ident;
+    let list_ty = &full_type.out_ty;
+    let ty = &inner_type.out_ty;
+    let read_element = shape::read(element);
+    quote_spanned! {field_ident.span()=>
+        let #field_ident = {
+            let terminator: #ty = #terminator;
+            let mut list: #list_ty = Default::default();
+            loop {
+                let element = #read_element
+                    .map_err(|cause| cause.read_list(#struct_name, #field_name, list.len()))?;
+                if element == terminator {
+                    break;
+                }
+                list.push(element);
+            }
+            list
+        };
+    }
+}
+
+pub(crate) fn write_until_last(
+    inner_type: &NormalField,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&inner_type.member));
+    let field_ident = &inner_type.ident;
+    quote_spanned! {field_ident.span()=>
+        if #field_ident.is_empty() {
+            return Err(::abstract_bits::ToBytesError::EmptyList {
+                field_name: #field_name,
+                struct_name: #struct_name,
+            });
+        }
+        for (i, element) in #field_ident.iter().enumerate() {
+            let is_last = i + 1 == #field_ident.len();
+            ::abstract_bits::LastMarked::write_marked(element, is_last, writer)?;
+        }
+    }
+}
+
+pub(crate) fn read_until_last(
+    full_type: &NormalField,
+    inner_type: &NormalField,
+    struct_name: &Literal,
+) -> TokenStream {
+    let field_name = Literal::string(&member_name(&full_type.member));
+    let field_ident = &full_type.ident;
+    let list_ty = &full_type.out_ty;
+    let ty = &inner_type.out_ty;
+    quote_spanned! {field_ident.span()=>
+        let mut #field_ident: #list_ty = Vec::new();
+        loop {
+            let (element, is_last) = <#ty as ::abstract_bits::LastMarked>::read_marked(reader)
+                .map_err(|cause| cause.read_list(#struct_name, #field_name,
+                    #field_ident.len()))?;
+            #field_ident.push(element);
+            if is_last {
+                break;
+            }
+        }
+    }
+}
+
+/// At least one element (or the terminator) is always present
+pub(crate) fn min_bits(element: &Shape) -> TokenStream {
+    shape::min_bits(element)
+}
+
+pub(crate) fn max_bits() -> TokenStream {
+    quote! { usize::MAX }
+}
@@ -0,0 +1,280 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Ident, Visibility};
+
+use crate::model::Flag;
+
+use super::is_primitive;
+
+pub fn flags(
+    vis: Visibility,
+    ident: Ident,
+    attrs: Vec<Attribute>,
+    flags: Vec<Flag>,
+    repr: Ident,
+    bits: usize,
+) -> TokenStream {
+    let consts = flags.iter().map(
+        |Flag {
+             attrs, ident, bit, ..
+         }| {
+            let bit = Literal::usize_unsuffixed(*bit);
+            quote! {
+                #(#attrs)*
+                pub const #ident: Self = Self(1 << #bit);
+            }
+        },
+    );
+    let names = flags.iter().map(|Flag { ident, name, .. }| {
+        let name = Literal::string(name);
+        quote! { (#name, Self::#ident) }
+    });
+    let all = flags.iter().fold(0u64, |all, flag| all | 1 << flag.bit);
+    let all = Literal::u64_unsuffixed(all);
+    let name = Literal::string(&ident.to_string());
+
+    let write_code = write(bits, &name);
+    let read_code = read(&repr, bits);
+    let operators = operators(&ident);
+    // the user's own derive prints the bits instead
+    let debug_impl = (!derives_debug(&attrs)).then(|| debug_impl(&ident, &name, names));
+
+    quote! {
+        #(#attrs)*
+        #vis struct #ident(#repr);
+
+        impl #ident {
+            #(#consts)*
+
+            /// No flags set
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+            /// Every known flag set
+            pub const fn all() -> Self {
+                Self(#all)
+            }
+            pub const fn bits(&self) -> #repr {
+                self.0
+            }
+            /// Keeps bits that do not correspond to a flag
+            pub const fn from_bits_retain(bits: #repr) -> Self {
+                Self(bits)
+            }
+            /// Drops bits that do not correspond to a flag
+            pub const fn from_bits_truncate(bits: #repr) -> Self {
+                Self(bits & Self::all().0)
+            }
+            pub const fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+            /// Whether all flags in `other` are set
+            pub const fn contains(&self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+            /// Whether any flag in `other` is set
+            pub const fn intersects(&self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+            pub const fn union(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+            pub const fn intersection(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+            pub const fn difference(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+            pub const fn symmetric_difference(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+            /// Flips every known flag, bits that do not correspond to a flag
+            /// are cleared
+            pub const fn complement(self) -> Self {
+                Self(!self.0 & Self::all().0)
+            }
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+            pub fn toggle(&mut self, other: Self) {
+                self.0 ^= other.0;
+            }
+            pub fn set(&mut self, other: Self, value: bool) {
+                if value {
+                    self.insert(other)
+                } else {
+                    self.remove(other)
+                }
+            }
+        }
+
+        #operators
+
+        #debug_impl
+
+        #[automatically_derived]
+        impl ::abstract_bits::AbstractBits for #ident {
+            const MIN_BITS: usize = #bits;
+            const MAX_BITS: usize = #bits;
+
+            fn write_abstract_bits(&self, writer: &mut ::abstract_bits::BitWriter)
+            -> Result<(), ::abstract_bits::ToBytesError> {
+                #write_code
+            }
+            fn read_abstract_bits(reader: &mut ::abstract_bits::BitReader)
+            -> Result<Self, ::abstract_bits::FromBytesError>
+            where
+                Self: Sized
+            {
+                #read_code
+            }
+        }
+    }
+}
+
+fn debug_impl(
+    ident: &Ident,
+    name: &Literal,
+    names: impl Iterator<Item = TokenStream>,
+) -> TokenStream {
+    quote! {
+        impl ::core::fmt::Debug for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#name)?;
+                f.write_str("(")?;
+                let mut remaining = self.0;
+                let mut first = true;
+                // every flag with its name in order of the bit it occupies
+                let flags: &[(&str, Self)] = &[#(#names),*];
+                for (name, flag) in flags {
+                    if self.0 & flag.0 != flag.0 {
+                        continue;
+                    }
+                    if !first {
+                        f.write_str(" | ")?;
+                    }
+                    first = false;
+                    f.write_str(name)?;
+                    remaining &= !flag.0;
+                }
+                if remaining != 0 {
+                    if !first {
+                        f.write_str(" | ")?;
+                    }
+                    first = false;
+                    write!(f, "{:#x}", remaining)?;
+                }
+                if first {
+                    f.write_str("empty")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn derives_debug(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| {
+            attr.parse_args_with(
+                Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+            )
+            .ok()
+        })
+        .flatten()
+        .any(|path| path.segments.last().is_some_and(|s| s.ident == "Debug"))
+}
+
+fn operators(ident: &Ident) -> TokenStream {
+    let binary = [
+        (quote! { BitOr }, quote! { bitor }, quote! { union }),
+        (
+            quote! { BitAnd },
+            quote! { bitand },
+            quote! { intersection },
+        ),
+        (
+            quote! { BitXor },
+            quote! { bitxor },
+            quote! { symmetric_difference },
+        ),
+        (quote! { Sub }, quote! { sub }, quote! { difference }),
+    ]
+    .into_iter()
+    .map(|(op, method, set_op)| {
+        let op_assign = quote::format_ident!("{op}Assign");
+        let method_assign = quote::format_ident!("{method}_assign");
+        quote! {
+            impl ::core::ops::#op for #ident {
+                type Output = Self;
+                fn #method(self, other: Self) -> Self {
+                    self.#set_op(other)
+                }
+            }
+            impl ::core::ops::#op_assign for #ident {
+                fn #method_assign(&mut self, other: Self) {
+                    *self = Self(self.0).#set_op(other);
+                }
+            }
+        }
+    });
+    quote! {
+        #(#binary)*
+        impl ::core::ops::Not for #ident {
+            type Output = Self;
+            fn not(self) -> Self {
+                self.complement()
+            }
+        }
+        impl ::core::iter::FromIterator<#ident> for #ident {
+            fn from_iter<I: IntoIterator<Item = Self>>(iter: I) -> Self {
+                iter.into_iter().fold(Self::empty(), Self::union)
+            }
+        }
+    }
+}
+
+fn write(bits: usize, name: &Literal) -> TokenStream {
+    let max = Literal::u64_suffixed(u64::MAX >> (64 - bits));
+    let to_wire_type = if let Some(ty) = is_primitive(bits) {
+        quote! { self.0 as #ty }
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! { #utype::new(self.0 as _) }
+    };
+    quote! {
+        // bits retained from a wider value may not fit
+        if self.0 as u64 > #max {
+            return Err(::abstract_bits::ToBytesError::ValueTooLarge {
+                field_name: "bits",
+                struct_name: #name,
+                max: #max,
+                got: self.0 as u64,
+            });
+        }
+        ::abstract_bits::AbstractBits::write_abstract_bits(&(#to_wire_type), writer)
+    }
+}
+
+fn read(repr: &Ident, bits: usize) -> TokenStream {
+    if let Some(ty) = is_primitive(bits) {
+        quote! {
+            let bits = <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)?;
+            Ok(Self(bits as #repr))
+        }
+    } else {
+        let utype: syn::Type = syn::parse_str(&format!("::abstract_bits::u{bits}"))
+            .expect("valid type path");
+        quote! {
+            let bits = <#utype as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)?;
+            Ok(Self(bits.value() as #repr))
+        }
+    }
+}
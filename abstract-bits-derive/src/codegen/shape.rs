@@ -0,0 +1,106 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{ToTokens, quote};
+
+use crate::model::Shape;
+
+/// Expression reading one element, evaluates to a `Result`
+pub fn read(shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::UInt(bits) => {
+            let utype = utype(*bits);
+            quote! {
+                <#utype as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+                    .map(|value| value.value())
+            }
+        }
+        Shape::Array { inner, length } => {
+            let read_inner = read(inner);
+            quote! {
+                ::abstract_bits::read_array::<_, { #length }>(reader, |reader| #read_inner)
+            }
+        }
+        Shape::Bit => quote! {
+            <bool as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+        },
+        Shape::Other(ty) => quote! {
+            <#ty as ::abstract_bits::AbstractBits>::read_abstract_bits(reader)
+        },
+    }
+}
+
+/// Writes the element `value` refers to. Integers too large for their width
+/// are an error.
+pub fn write(
+    shape: &Shape,
+    value: TokenStream,
+    field_name: &Literal,
+    struct_name: &Literal,
+) -> TokenStream {
+    match shape {
+        Shape::UInt(bits) => {
+            let utype = utype(*bits);
+            let max = Literal::u64_suffixed(u64::MAX >> (64 - bits));
+            quote! {
+                {
+                    let value = *#value;
+                    if value as u64 > #max {
+                        return Err(::abstract_bits::ToBytesError::ValueTooLarge {
+                            field_name: #field_name,
+                            struct_name: #struct_name,
+                            max: #max,
+                            got: value as u64,
+                        });
+                    }
+                    ::abstract_bits::AbstractBits::write_abstract_bits(&#utype::new(value), writer)?;
+                }
+            }
+        }
+        Shape::Array { inner, .. } => {
+            let write_inner = write(inner, quote! { element }, field_name, struct_name);
+            quote! {
+                for element in #value {
+                    #write_inner
+                }
+            }
+        }
+        // iterating a `BitVec` gives proxies that deref to `bool`
+        Shape::Bit => quote! {
+            ::abstract_bits::AbstractBits::write_abstract_bits(&*#value, writer)?;
+        },
+        Shape::Other(_) => quote! {
+            ::abstract_bits::AbstractBits::write_abstract_bits(#value, writer)?;
+        },
+    }
+}
+
+pub fn min_bits(shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::UInt(bits) => {
+            Literal::usize_unsuffixed(*bits as usize).into_token_stream()
+        }
+        Shape::Array { inner, length } => {
+            let inner = min_bits(inner);
+            quote! { (#inner) * (#length) }
+        }
+        Shape::Bit => quote! { 1 },
+        Shape::Other(ty) => quote! { <#ty as ::abstract_bits::AbstractBits>::MIN_BITS },
+    }
+}
+
+pub fn max_bits(shape: &Shape) -> TokenStream {
+    match shape {
+        Shape::UInt(bits) => {
+            Literal::usize_unsuffixed(*bits as usize).into_token_stream()
+        }
+        Shape::Array { inner, length } => {
+            let inner = max_bits(inner);
+            quote! { usize::saturating_mul(#inner, #length) }
+        }
+        Shape::Bit => quote! { 1 },
+        Shape::Other(ty) => quote! { <#ty as ::abstract_bits::AbstractBits>::MAX_BITS },
+    }
+}
+
+fn utype(bits: u8) -> syn::Type {
+    syn::parse_str(&format!("::abstract_bits::u{bits}")).expect("valid type path")
+}
@@ -7,8 +7,6 @@ use codegen::codegen;
 mod errors;
 
 /// # Warning
-/// - When applied to an enum (which does not contain values) 
-/// that enum must be Copy.
 /// - Any derives should be applied **after** the `abstract_bits` macro.
 ///
 /// # Example:
@@ -36,6 +34,7 @@ mod errors;
 ///     pub destination_address: Nwk,
 ///     pub path_cost: u8,
 ///     pub destination_eui64: Option<Eui64>,
+///     #[abstract_bits(rest)]
 ///     pub tlvs: Vec<u8>,
 /// }
 /// ```
@@ -1,8 +1,14 @@
-use proc_macro_error2::{OptionExt, abort};
+use std::ops::Range;
+
+use proc_macro_error2::abort;
 use proc_macro2::{Span, TokenStream, TokenTree};
-use syn::parse_quote_spanned;
+use quote::{ToTokens, format_ident};
 use syn::spanned::Spanned;
-use syn::{Attribute, GenericArgument, Ident, PathArguments, Visibility};
+use syn::{Attribute, GenericArgument, Ident, Member, PathArguments, Visibility};
+use syn::{parse_quote, parse_quote_spanned};
+
+mod options;
+use options::Options;
 
 #[derive(Debug)]
 pub struct Model {
@@ -13,21 +19,107 @@ pub struct Model {
 }
 
 #[derive(Debug)]
-pub struct EmptyVariant {
+pub struct Variant {
     pub attrs: Vec<Attribute>,
     pub ident: Ident,
-    pub discriminant: usize,
+    pub discriminant: Discriminant,
+    pub fields: VariantFields,
+}
+
+/// How the discriminant of an enum is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// As an unsigned number
+    Binary,
+    /// As the position of the only bit set
+    OneHot,
+    /// As the number of consecutive low bits set
+    Thermometer,
+}
+
+impl Encoding {
+    fn max_discriminant(self, bits: usize) -> u64 {
+        match self {
+            Encoding::Binary => u64::MAX >> (64 - bits.min(64)),
+            Encoding::OneHot => bits as u64 - 1,
+            Encoding::Thermometer => bits as u64,
+        }
+    }
+
+    /// Enough bits to store the largest discriminant
+    fn bits_needed(self, largest: usize) -> usize {
+        match self {
+            Encoding::Binary => (usize::BITS - largest.leading_zeros()) as usize,
+            Encoding::OneHot => largest + 1,
+            Encoding::Thermometer => largest,
+        }
+        .max(1)
+    }
+}
+
+/// A named bit of a flag set
+#[derive(Debug)]
+pub struct Flag {
+    pub attrs: Vec<Attribute>,
+    /// Name of the constant, the variant name in SCREAMING_SNAKE_CASE
+    pub ident: Ident,
+    pub name: String,
+    pub bit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Discriminant {
+    Value(usize),
+    /// Value only known to the compiler, such as a const. Variants without
+    /// a discriminant that follow it count up from it.
+    Expr {
+        expr: Box<syn::Expr>,
+        offset: usize,
+    },
+    /// Matches every value no other variant matches. The variant holds the
+    /// raw value, which has the given type.
+    Other(Box<syn::Type>),
+    /// Matches every value from start up to and including end. The variant
+    /// can hold the raw value.
+    Range {
+        start: usize,
+        end: usize,
+        raw: Option<Box<syn::Type>>,
+    },
+}
+
+#[derive(Debug)]
+pub enum VariantFields {
+    Unit,
+    Named(Vec<Field>),
+    Unnamed(Vec<Field>),
+}
+
+impl VariantFields {
+    pub fn fields(&self) -> &[Field] {
+        match self {
+            VariantFields::Unit => &[],
+            VariantFields::Named(fields) | VariantFields::Unnamed(fields) => fields,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Type {
     NormalStruct(Vec<Field>),
-    UnitStruct(syn::Field),
+    TupleStruct(Vec<Field>),
     Enum {
         bits: usize,
-        variants: Vec<EmptyVariant>,
+        variants: Vec<Variant>,
         // Extracted as Ident from parsed AST, no reason to change that
         repr_type: Ident,
+        encoding: Encoding,
+    },
+    /// A set of independent flags, each occupying one bit
+    Flags {
+        bits: usize,
+        flags: Vec<Flag>,
+        repr_type: Ident,
     },
 }
 
@@ -35,6 +127,9 @@ pub enum Type {
 pub struct NormalField {
     pub vis: Visibility,
     pub attrs: Vec<Attribute>,
+    /// The field name or for tuple structs its index
+    pub member: Member,
+    /// The local variable holding the field while (de)serializing
     pub ident: Ident,
     pub out_ty: syn::Type,
     pub bits: Option<u8>,
@@ -50,8 +145,26 @@ fn out_ty_from_padding(padding: u8, span: Span) -> syn::Type {
     }
 }
 
+/// Local variable name for a field, tuple struct fields need a made up name
+pub fn binding(member: &Member) -> Ident {
+    match member {
+        Member::Named(ident) => ident.clone(),
+        Member::Unnamed(index) => {
+            format_ident!("field_{}", index.index, span = index.span)
+        }
+    }
+}
+
+/// Name of a field as used in error messages
+pub fn member_name(member: &Member) -> String {
+    match member {
+        Member::Named(ident) => ident.to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
 impl NormalField {
-    fn from(field: syn::Field) -> Self {
+    fn from(field: syn::Field, member: Member) -> Self {
         let mut bits = None;
         let mut out_ty = field.ty.clone();
         if let Ok(padding) = padding_from_type(&field.ty) {
@@ -63,68 +176,238 @@ impl NormalField {
 
         NormalField {
             vis: field.vis,
-            attrs: field.attrs,
-            ident: field.ident.expect("unit struct not handled by NormalField"),
+            attrs: options::strip(field.attrs),
+            ident: binding(&member),
+            member,
             out_ty,
             bits,
         }
     }
 }
 
+/// Layout of array and list elements. Like plain fields integers of unusual
+/// widths are kept in the smallest primitive that fits them.
+#[derive(Debug, Clone)]
+pub enum Shape {
+    UInt(u8),
+    Array {
+        inner: Box<Shape>,
+        length: syn::Expr,
+    },
+    /// A bit of a `BitVec`, read and written as a `bool`
+    Bit,
+    /// Any other type, including arrays without such integers
+    Other(syn::Type),
+}
+
+impl Shape {
+    fn from(ty: &syn::Type) -> Self {
+        if let Ok(bits) = padding_from_type(ty) {
+            if !matches!(bits, 8 | 16 | 32 | 64) {
+                // checks the width
+                out_ty_from_padding(bits, ty.span());
+                return Shape::UInt(bits);
+            }
+        }
+        if let syn::Type::Array(array) = ty {
+            let inner = Shape::from(&array.elem);
+            if !matches!(inner, Shape::Other(_)) {
+                return Shape::Array {
+                    inner: Box::new(inner),
+                    length: array.len.clone(),
+                };
+            }
+        }
+        Shape::Other(ty.clone())
+    }
+
+    /// The type in the generated struct
+    pub fn out_ty(&self) -> syn::Type {
+        match self {
+            Shape::UInt(bits) => out_ty_from_padding(*bits, Span::call_site()),
+            Shape::Array { inner, length } => {
+                let inner = inner.out_ty();
+                parse_quote_spanned!(length.span()=> [#inner; #length])
+            }
+            Shape::Bit => parse_quote!(bool),
+            Shape::Other(ty) => ty.clone(),
+        }
+    }
+}
+
+/// What decides whether an `Option` field is `Some`
+#[derive(Debug)]
+pub enum Presence {
+    /// An earlier [`Field::ControlOption`]
+    Controlled,
+    /// An expression that may reference earlier fields, `uses` lists those
+    /// it references.
+    Condition { expr: syn::Expr, uses: Vec<Ident> },
+    /// Always present on the wire, this value means `None`
+    NoneValue(syn::Expr),
+    /// Present if enough input remains. Can only be followed by more of
+    /// these, `after` is the one before it if any.
+    IfRemaining { after: Option<Ident> },
+}
+
+/// A controller that also appears in the struct
+#[derive(Debug, Clone)]
+pub struct Kept {
+    pub field: NormalField,
+    pub on_write: OnWrite,
+}
+
+/// What writing does with the value of a [`Kept`] controller
+#[derive(Debug, Clone, Copy)]
+pub enum OnWrite {
+    /// Ignore it and write what the controlled field needs
+    Recompute,
+    /// Write it after making sure it matches the controlled field
+    Check,
+}
+
+/// How a [`Field::ControlList`] stores the length of its list
+#[derive(Debug, Clone)]
+pub struct LengthTransform {
+    /// Value stored for a list of length `len`
+    pub encoded: syn::Expr,
+    /// Length of the list given the stored value `raw` as an `Option<i128>`,
+    /// `None` if no length gives that value
+    pub decoded: syn::Expr,
+    /// Earlier fields used by either expression
+    pub uses: Vec<Ident>,
+}
+
+/// What decides where a `Vec` field ends
+#[derive(Debug)]
+pub enum ListLength {
+    /// An earlier [`Field::ControlList`]
+    Controlled {
+        bits: usize,
+        transform: Option<Box<LengthTransform>>,
+    },
+    /// The end of the input
+    Rest,
+    /// A length of this many bits right before the list
+    Prefixed { bits: usize },
+    /// An element equal to this value, which is not part of the list
+    Terminator(syn::Expr),
+    /// The first element marked as last by its [`Field::IsLast`]
+    UntilLast,
+}
+
 #[derive(Debug)]
 pub enum Field {
     Normal(NormalField),
     Option {
         full_type: NormalField,
         inner_type: NormalField,
+        presence: Presence,
     },
     List {
         full_type: NormalField,
         inner_type: NormalField,
-        max_len: usize,
+        element: Shape,
+        length: ListLength,
     },
     Array {
         length: syn::Expr,
         inner_type: syn::Type,
-        field: syn::Field,
+        element: Shape,
+        field: NormalField,
     },
     ControlList {
-        controlled: Ident,
+        controlled: Member,
+        /// Further lists that always have the same length
+        shared: Vec<Member>,
+        bits: usize,
+        /// Stores something other than the length itself
+        transform: Option<LengthTransform>,
+        kept: Option<Kept>,
+    },
+    ControlOption {
+        controlled: Member,
+        /// A `bool` controller is one bit
+        bits: usize,
+        /// Values meaning the option is `Some`, the first is written for `Some`
+        present: Vec<u64>,
+        /// Value written for `None`
+        absent: u64,
+        kept: Option<Kept>,
+    },
+    ControlTag {
+        controlled: Member,
+        bits: usize,
+        kept: Option<Kept>,
+    },
+    /// Bit `i` says whether the `i`-th option in `controlled` is present
+    PresenceBitmap {
+        controlled: Vec<Member>,
         bits: usize,
     },
-    ControlOption(Ident),
+    /// Enum without its tag, the tag is stored in a [`Field::ControlTag`]
+    Tagged(NormalField),
+    /// Bit marking the last element of a list, see [`ListLength::UntilLast`]
+    IsLast(Member),
+    /// Constant written as is, reading anything else is an error
+    Magic {
+        bits: usize,
+        value: u64,
+    },
+    /// Filled in once the struct is written, checked once it is read
+    Checksum {
+        /// Implements `abstract_bits::Checksum`
+        algorithm: syn::Type,
+        bits: usize,
+        /// Position among the fields, names its local variables
+        index: usize,
+        /// Indices of the fields it is computed over
+        covers: Range<usize>,
+        /// `over = <range>` as written, resolved into `covers` once all
+        /// fields are known
+        over: Option<syn::ExprRange>,
+    },
     PaddBits(u8),
 }
 
 impl Field {
-    pub fn option_stripped(&self) -> Option<&NormalField> {
+    /// Option fields whose presence is stored in a controller
+    pub fn controlled_option(&self) -> Option<&NormalField> {
         match self {
             Field::Option {
-                inner_type: field, ..
+                inner_type: field,
+                presence: Presence::Controlled,
+                ..
             } => Some(field),
             _ => None,
         }
     }
 
+    /// Whether this decides if the option `member` is present
+    fn controls_option(&self, member: &Member) -> bool {
+        match self {
+            Field::ControlOption { controlled, .. } => controlled == member,
+            Field::PresenceBitmap { controlled, .. } => controlled.contains(member),
+            _ => false,
+        }
+    }
+
     pub fn needed_in_struct_def(&self) -> Option<NormalField> {
         match self {
             Field::Normal(field)
+            | Field::Tagged(field)
             | Field::Option {
                 full_type: field, ..
             }
             | Field::List {
                 full_type: field, ..
-            } => Some(field.clone()),
-            Field::Array { field, .. } => Some(NormalField {
-                vis: field.vis.clone(),
-                attrs: field.attrs.clone(),
-                ident: field
-                    .ident
-                    .clone()
-                    .expect("code is not run for unit structs"),
-                out_ty: field.ty.clone(),
-                bits: None,
-            }),
+            }
+            | Field::Array { field, .. } => Some(field.clone()),
+            Field::ControlList { kept, .. }
+            | Field::ControlOption { kept, .. }
+            | Field::ControlTag { kept, .. } => {
+                kept.as_ref().map(|kept| kept.field.clone())
+            }
             _ => None,
         }
     }
@@ -132,7 +415,7 @@ impl Field {
 
 fn padding_from_type(ty: &syn::Type) -> Result<u8, (&'static str, Span)> {
     let syn::Type::Path(ty) = ty else {
-        abort!(ty.span(), "only normal types are supported");
+        return Err(("field is not a path type", ty.span()));
     };
 
     let end = ty.path.segments.last().expect("type can not be empty");
@@ -146,58 +429,538 @@ fn padding_from_type(ty: &syn::Type) -> Result<u8, (&'static str, Span)> {
 }
 
 impl Field {
-    fn from(field: syn::Field, previous_fields: &[Field]) -> Self {
-        let ident = field
-            .ident
-            .as_ref()
-            .expect("unit structs are not tranformed into model::Field");
-        if let Some(controlled) = controls_option(&field) {
-            Self::ControlOption(controlled)
-        } else if let Some(controlled) = controls_list(&field) {
+    fn from(field: syn::Field, member: Member, previous_fields: &[Field]) -> Self {
+        let mut options = Options::from_attrs(&field.attrs);
+        let is_reserved = options.take_flag("reserved")
+            || field
+                .ident
+                .as_ref()
+                .is_some_and(|ident| ident == "reserved");
+        let mut condition = options.take_value("if");
+        let mut none_value = options.take_value("none_value");
+        let if_remaining = options.take_flag("if_remaining");
+        let is_rest = options.take_flag("rest");
+        let terminator = options.take_value("terminator");
+        let until_last = options.take_flag("until_last");
+        let len_prefix = options.take_value("len_prefix").map(|prefix| {
+            let ty: syn::Type = syn::parse2(prefix.to_token_stream())
+                .unwrap_or_else(|_| abort!(prefix, "Expected an unsigned integer type"));
+            padding_from_type(&ty).unwrap_or_else(|(msg, span)| abort!(span, msg))
+                as usize
+        });
+        let is_last = options.take_flag("is_last");
+        let over = options.take_value("over");
+        let ty_span = field.ty.span();
+        let keep = options.take_value("keep").map(|on_write| Kept {
+            field: NormalField::from(field.clone(), member.clone()),
+            on_write: match &on_write {
+                syn::Expr::Path(path) if path.path.is_ident("recompute") => {
+                    OnWrite::Recompute
+                }
+                syn::Expr::Path(path) if path.path.is_ident("check") => OnWrite::Check,
+                _ => abort!(on_write, "Expected `recompute` or `check`";
+                    help = "`keep = recompute` writes what the controlled field needs, \
+                    `keep = check` fails writing if the value does not match it"),
+            },
+        });
+        let mut list_length = match (is_rest, terminator, until_last, len_prefix) {
+            (false, None, false, None) => None,
+            (true, None, false, None) => Some(ListLength::Rest),
+            (false, Some(terminator), false, None) => {
+                Some(ListLength::Terminator(terminator))
+            }
+            (false, None, true, None) => Some(ListLength::UntilLast),
+            (false, None, false, Some(bits)) => Some(ListLength::Prefixed { bits }),
+            _ => abort!(ty_span, "A list can only end in one way";
+                help = "Use one of `rest`, `terminator`, `until_last` or `len_prefix`"),
+        };
+        if let (true, Some(other)) =
+            (if_remaining, condition.as_ref().or(none_value.as_ref()))
+        {
+            abort!(
+                other,
+                "An `if_remaining` Option can not have a condition or `none_value`"
+            );
+        }
+        if let (Some(condition), Some(_)) = (&condition, &none_value) {
+            abort!(
+                condition,
+                "An Option can not have both a condition and a `none_value`"
+            );
+        }
+        let mut parsed = if let Some(algorithm) = options.take_value("checksum") {
             let bits = padding_from_type(&field.ty)
                 .unwrap_or_else(|(msg, span)| abort!(span, msg));
-            Self::ControlList {
+            if bits > 64 {
+                abort!(ty_span, "A checksum field can be at most 64 bits");
+            }
+            let over = over.map(|over| match over {
+                syn::Expr::Range(range) => range,
+                other => abort!(other, "Expected a range of fields";
+                    help = "For example `over = kind..payload` or `over = kind..=len`"),
+            });
+            if over.is_none() && previous_fields.is_empty() {
+                abort!(ty_span, "A checksum in front needs to know what it covers";
+                    help = "Add `over = <first field>..`");
+            }
+            Self::Checksum {
+                algorithm: checksum_algorithm(algorithm),
+                bits: bits as usize,
+                index: previous_fields.len(),
+                covers: 0..0,
+                over,
+            }
+        } else if let Some(over) = over {
+            abort!(over, "Only checksums can have `over`");
+        } else if let Some(value) = options.take_value("magic") {
+            let bits = padding_from_type(&field.ty)
+                .unwrap_or_else(|(msg, span)| abort!(span, msg));
+            if bits > 64 {
+                abort!(ty_span, "A magic field can be at most 64 bits");
+            }
+            let value_span = value.span();
+            let value = require_usize(value) as u64;
+            if value > u64::MAX >> (64 - bits) {
+                abort!(value_span, "Magic value {} does not fit into {} bits", value, bits);
+            }
+            Self::Magic {
+                bits: bits as usize,
+                value,
+            }
+        } else if let Some(controlled) = options.take_value("presence_of") {
+            let present = options.take_value("present").map(require_values);
+            presence_controller(&field, controlled_member(&controlled, "presence_of"), present)
+        } else if let Some(controlled) = options.take_value("absence_of") {
+            let present = Some(vec![0]);
+            presence_controller(&field, controlled_member(&controlled, "absence_of"), present)
+        } else if let Some(controlled) = options.take_value("presence_bitmap") {
+            let bits = padding_from_type(&field.ty)
+                .unwrap_or_else(|(msg, span)| abort!(span, msg));
+            let controlled: Vec<_> = match controlled {
+                syn::Expr::Tuple(tuple) => tuple
+                    .elems
+                    .iter()
+                    .map(|expr| controlled_member(expr, "presence_bitmap"))
+                    .collect(),
+                expr => vec![controlled_member(&expr, "presence_bitmap")],
+            };
+            if controlled.len() > bits as usize {
+                abort!(ty_span, "A presence bitmap of {} bits can not control {} options",
+                    bits, controlled.len());
+            }
+            Self::PresenceBitmap {
                 controlled,
                 bits: bits as usize,
             }
-        } else if ident == "reserved" {
+        } else if let Some(controlled) = options.take_value("length_of") {
+            let bits = padding_from_type(&field.ty)
+                .unwrap_or_else(|(msg, span)| abort!(span, msg));
+            if bits > 64 {
+                abort!(ty_span, "A length controller can be at most 64 bits");
+            }
+            let transform = options
+                .take_value("encoded")
+                .map(|encoded| length_transform(encoded, options.take_value("decoded"),
+                    previous_fields));
+            // one length for several lists: `length_of = (addresses, costs)`
+            let mut controlled = match controlled {
+                syn::Expr::Tuple(tuple) => tuple
+                    .elems
+                    .iter()
+                    .map(|expr| controlled_member(expr, "length_of"))
+                    .collect(),
+                expr => vec![controlled_member(&expr, "length_of")],
+            };
+            if controlled.is_empty() {
+                abort!(ty_span, "`length_of` needs at least one list");
+            }
+            Self::ControlList {
+                controlled: controlled.remove(0),
+                shared: controlled,
+                bits: bits as usize,
+                transform,
+                kept: None,
+            }
+        } else if let Some(controlled) = options.take_value("tag_of") {
+            let bits = padding_from_type(&field.ty)
+                .unwrap_or_else(|(msg, span)| abort!(span, msg));
+            Self::ControlTag {
+                controlled: controlled_member(&controlled, "tag_of"),
+                bits: bits as usize,
+                kept: None,
+            }
+        } else if is_last {
+            if !matches!(&field.ty, syn::Type::Path(path) if path.path.is_ident("bool")) {
+                abort!(ty_span, "An `is_last` marker must be a `bool`");
+            }
+            if previous_fields.iter().any(|f| matches!(f, Field::IsLast(_))) {
+                abort!(ty_span, "A struct can only have one `is_last` marker");
+            }
+            Self::IsLast(member)
+        } else if is_reserved {
             let padding = padding_from_type(&field.ty)
                 .unwrap_or_else(|(msg, span)| abort!(span, msg));
             Self::PaddBits(padding)
+        } else if previous_fields.iter().any(|f| {
+            matches!(f, Field::ControlTag { controlled, .. } if *controlled == member)
+        }) {
+            Self::Tagged(NormalField::from(field, member))
         } else if let Some(option_stripped) = strip_option(field.clone()) {
+            let presence = match (condition.take(), none_value.take()) {
+                _ if if_remaining => Presence::IfRemaining {
+                    after: previous_fields.iter().rev().find_map(|f| match f {
+                        Field::Option {
+                            inner_type,
+                            presence: Presence::IfRemaining { .. },
+                            ..
+                        } => Some(inner_type.ident.clone()),
+                        _ => None,
+                    }),
+                },
+                (Some(expr), _) => Presence::Condition {
+                    uses: referenced_fields(&expr, previous_fields),
+                    expr,
+                },
+                (None, Some(none_value)) => Presence::NoneValue(none_value),
+                (None, None) => Presence::Controlled,
+            };
+            let inner_type = NormalField::from(option_stripped, member.clone());
+            if matches!(presence, Presence::IfRemaining { .. })
+                && inner_type.bits.is_some_and(|bits| bits < 8)
+            {
+                abort!(ty_span, "An `if_remaining` field must take up at least a byte";
+                    note = "Less than a byte could be the padding at the end of the input");
+            }
+            let mut full_type = NormalField::from(field, member);
+            // an Option<u11> becomes an Option<u16>
+            let inner_out_ty = &inner_type.out_ty;
+            full_type.out_ty = parse_quote_spanned!(full_type.out_ty.span()=>
+                Option<#inner_out_ty>);
             Self::Option {
-                inner_type: NormalField::from(option_stripped),
-                full_type: NormalField::from(field),
+                inner_type,
+                full_type,
+                presence,
+            }
+        } else if let Some((vec_stripped, element)) = strip_list(field.clone()) {
+            let length = list_length
+                .take()
+                .unwrap_or_else(|| length_from_control_list(&member, previous_fields));
+            if matches!(length, ListLength::Rest)
+                && matches!(element, Shape::Bit | Shape::UInt(..8))
+            {
+                abort!(ty_span, "The elements of a `rest` list must take up at least a byte";
+                    note = "Less than a byte could be the padding at the end of the input");
+            }
+            let mut inner_type = NormalField::from(vec_stripped, member.clone());
+            let mut full_type = NormalField::from(field, member);
+            inner_type.out_ty = element.out_ty();
+            if !matches!(element, Shape::Bit) {
+                // a Vec<[u4; 2]> becomes a Vec<[u8; 2]>
+                let inner_out_ty = &inner_type.out_ty;
+                full_type.out_ty = parse_quote_spanned!(full_type.out_ty.span()=>
+                    Vec<#inner_out_ty>);
             }
-        } else if let Some(vec_stripped) = strip_vec(field.clone()) {
             Self::List {
-                inner_type: NormalField::from(vec_stripped),
-                max_len: max_size_from_control_list(&ident, previous_fields),
-                full_type: NormalField::from(field),
+                inner_type,
+                element,
+                length,
+                full_type,
             }
         } else if let syn::Type::Array(a) = &field.ty {
+            let element = Shape::from(&a.elem);
+            let (inner_type, length) = (*a.elem.clone(), a.len.clone());
+            let mut field = NormalField::from(field, member);
+            field.out_ty = Shape::from(&field.out_ty).out_ty();
             Self::Array {
-                inner_type: *a.elem.clone(),
-                length: a.len.clone(),
+                inner_type,
+                length,
+                element,
                 field,
             }
         } else {
-            Self::Normal(NormalField::from(field))
+            Self::Normal(NormalField::from(field, member))
+        };
+        if let Some(condition) = condition {
+            abort!(condition, "Only `Option` fields can have a condition");
+        }
+        if let Some(none_value) = none_value {
+            abort!(none_value, "Only `Option` fields can have a `none_value`");
+        }
+        if if_remaining && !matches!(parsed, Field::Option { .. }) {
+            abort!(ty_span, "Only `Option` fields can be `if_remaining`");
+        }
+        if let Some(keep) = keep {
+            match &mut parsed {
+                Field::ControlList { kept, .. }
+                | Field::ControlOption { kept, .. }
+                | Field::ControlTag { kept, .. } => *kept = Some(keep),
+                _ => abort!(ty_span, "Only controllers can be kept in the struct"),
+            }
+        }
+        if list_length.is_some() {
+            abort!(
+                ty_span,
+                "Only `Vec` and `BitVec` fields can have `rest`, \
+                `terminator`, `until_last` or `len_prefix`"
+            );
+        }
+        options.reject_remaining();
+        parsed
+    }
+}
+
+fn presence_controller(
+    field: &syn::Field,
+    controlled: Member,
+    present: Option<Vec<u64>>,
+) -> Field {
+    let bits = match &field.ty {
+        syn::Type::Path(path) if path.path.is_ident("bool") => 1,
+        ty => {
+            padding_from_type(ty).unwrap_or_else(|(msg, span)| abort!(span, msg)) as usize
+        }
+    };
+    if bits > 64 {
+        abort!(field.ty, "A presence controller can be at most 64 bits");
+    }
+    let present = match present {
+        Some(present) => present,
+        None if bits == 1 => vec![1],
+        None => abort!(field.ty, "A presence controller wider than one bit needs the \
+            values that mean present"; help = "For example: \
+            #[abstract_bits(presence_of = {}, present = 1 | 3)]", member_name(&controlled)),
+    };
+    let max = u64::MAX >> (64 - bits);
+    if let Some(too_large) = present.iter().find(|v| **v > max) {
+        abort!(
+            field.ty,
+            "Present value {} does not fit into {} bits",
+            too_large,
+            bits
+        );
+    }
+    let Some(absent) = (0..=max).find(|v| !present.contains(v)) else {
+        abort!(
+            field.ty,
+            "Every value of the controller means present, the option could \
+            never be None"
+        );
+    };
+    Field::ControlOption {
+        controlled,
+        bits,
+        present,
+        absent,
+        kept: None,
+    }
+}
+
+/// Values separated by `|`, as in `1 | 3`
+fn require_values(expr: syn::Expr) -> Vec<u64> {
+    match expr {
+        syn::Expr::Binary(syn::ExprBinary {
+            left,
+            op: syn::BinOp::BitOr(_),
+            right,
+            ..
+        }) => {
+            let mut values = require_values(*left);
+            values.extend(require_values(*right));
+            values
+        }
+        expr => vec![require_usize(expr) as u64],
+    }
+}
+
+/// Earlier fields whose name appears in the expression
+fn referenced_fields(expr: &syn::Expr, previous_fields: &[Field]) -> Vec<Ident> {
+    fn idents(tokens: TokenStream, found: &mut Vec<Ident>) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => found.push(ident),
+                TokenTree::Group(group) => idents(group.stream(), found),
+                _ => (),
+            }
+        }
+    }
+    let mut found = Vec::new();
+    idents(expr.to_token_stream(), &mut found);
+    previous_fields
+        .iter()
+        .filter_map(Field::needed_in_struct_def)
+        .map(|f| f.ident)
+        .filter(|ident| found.contains(ident))
+        .collect()
+}
+
+fn length_transform(
+    encoded: syn::Expr,
+    decoded: Option<syn::Expr>,
+    previous_fields: &[Field],
+) -> LengthTransform {
+    if !mentions(&encoded, "len") {
+        abort!(encoded, "The encoded length must be computed from `len`";
+            help = "For example: #[abstract_bits(length_of = data, encoded = len - 1)]");
+    }
+    let decoded = match decoded {
+        Some(decoded) => parse_quote_spanned!(decoded.span()=> Some(#decoded)),
+        None => {
+            let raw: syn::Expr = parse_quote_spanned!(encoded.span()=> Some(raw));
+            invert_length(&encoded, raw).unwrap_or_else(|| {
+                abort!(encoded, "Can not work out the length from the stored value";
+                help = "Add `decoded = <expr>` computing the length from the stored \
+                value `raw`")
+            })
+        }
+    };
+    let mut uses = referenced_fields(&encoded, previous_fields);
+    for ident in referenced_fields(&decoded, previous_fields) {
+        if !uses.contains(&ident) {
+            uses.push(ident);
         }
     }
+    LengthTransform {
+        encoded,
+        decoded,
+        uses,
+    }
 }
 
-fn max_size_from_control_list(ident: &syn::Ident, previous_fields: &[Field]) -> usize {
-    if let Some(bits) = previous_fields.iter().find_map(|f| match f {
+/// Undoes additions, subtractions, multiplications and divisions applied to
+/// `len` one at a time, `None` if `expr` is not built from only those. The
+/// input is untrusted, so the operations are checked and a failing one makes
+/// the result `None`. The result is usable in constants.
+fn invert_length(expr: &syn::Expr, stored: syn::Expr) -> Option<syn::Expr> {
+    use syn::BinOp;
+    match expr {
+        syn::Expr::Path(path) if path.path.is_ident("len") => Some(stored),
+        syn::Expr::Paren(paren) => invert_length(&paren.expr, stored),
+        syn::Expr::Binary(binary) => {
+            let (left, right) = (&binary.left, &binary.right);
+            match (mentions(left, "len"), mentions(right, "len")) {
+                (true, false) => {
+                    let step = match binary.op {
+                        BinOp::Add(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_sub(raw, #right))
+                        }
+                        BinOp::Sub(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_add(raw, #right))
+                        }
+                        BinOp::Mul(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_div(raw, #right))
+                        }
+                        BinOp::Div(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_mul(raw, #right))
+                        }
+                        _ => return None,
+                    };
+                    invert_length(left, and_then(stored, step))
+                }
+                (false, true) => {
+                    let step = match binary.op {
+                        BinOp::Add(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_sub(raw, #left))
+                        }
+                        BinOp::Sub(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_sub(#left, raw))
+                        }
+                        BinOp::Mul(_) => {
+                            parse_quote_spanned!(expr.span()=> i128::checked_div(raw, #left))
+                        }
+                        _ => return None,
+                    };
+                    invert_length(right, and_then(stored, step))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `step` on the value `raw` held by `stored`, written as a match so it can be
+/// used in constants
+fn and_then(stored: syn::Expr, step: syn::Expr) -> syn::Expr {
+    parse_quote_spanned!(step.span()=> match #stored { Some(raw) => #step, None => None })
+}
+
+/// Whether `name` occurs anywhere in `expr`
+fn mentions(expr: &syn::Expr, name: &str) -> bool {
+    fn search(tokens: TokenStream, name: &str) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == name,
+            TokenTree::Group(group) => search(group.stream(), name),
+            _ => false,
+        })
+    }
+    search(expr.to_token_stream(), name)
+}
+
+/// Controllers name the field they control or, in tuple structs, its index
+fn controlled_member(expr: &syn::Expr, option: &str) -> Member {
+    match expr {
+        syn::Expr::Path(path) if path.path.get_ident().is_some() => {
+            Member::Named(path.path.get_ident().expect("just checked").clone())
+        }
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(index),
+            ..
+        }) => Member::Unnamed(syn::Index {
+            index: index
+                .base10_parse()
+                .unwrap_or_else(|_| abort!(index.span(), "not a valid field index")),
+            span: index.span(),
+        }),
+        _ => abort!(expr.span(), "invalid abstract_bits attribute";
+            help = "The syntax is: #[abstract_bits({} = <field>)] with field the \
+            name of a later field or, in a tuple struct, its index", option),
+    }
+}
+
+/// The built in algorithms have short names: `checksum = crc16_kermit`
+fn checksum_algorithm(algorithm: syn::Expr) -> syn::Type {
+    let syn::Expr::Path(path) = algorithm else {
+        abort!(algorithm, "Expected the name of a checksum algorithm";
+            help = "Use one of `crc16_kermit`, `crc32`, `sum8`, `xor8` or a type \
+            implementing `abstract_bits::Checksum`")
+    };
+    let built_in = path.path.get_ident().and_then(|ident| {
+        Some(match ident.to_string().as_str() {
+            "crc16_kermit" => "Crc16Kermit",
+            "crc32" => "Crc32",
+            "sum8" => "Sum8",
+            "xor8" => "Xor8",
+            _ => return None,
+        })
+    });
+    match built_in {
+        Some(name) => {
+            syn::parse_str(&format!("::abstract_bits::{name}")).expect("valid type path")
+        }
+        None => syn::Type::Path(syn::TypePath {
+            qself: None,
+            path: path.path,
+        }),
+    }
+}
+
+fn length_from_control_list(member: &Member, previous_fields: &[Field]) -> ListLength {
+    if let Some((bits, transform)) = previous_fields.iter().find_map(|f| match f {
         Field::ControlList {
-            controlled, bits, ..
-        } if controlled == ident => Some(bits),
+            controlled,
+            shared,
+            bits,
+            transform,
+            ..
+        } if controlled == member || shared.contains(member) => {
+            Some((*bits, transform.clone().map(Box::new)))
+        }
         _ => None,
     }) {
-        2usize.pow(*bits as u32)
+        ListLength::Controlled { bits, transform }
     } else {
         abort!(
-            ident,
+            member,
             "List without field controlling its length is not allowed"
         );
     }
@@ -207,6 +970,29 @@ fn strip_vec(field: syn::Field) -> Option<syn::Field> {
     strip_generic(field, "Vec")
 }
 
+/// A `Vec` or a `BitVec`, which holds `bool`s. Returns the field as its
+/// element type.
+fn strip_list(field: syn::Field) -> Option<(syn::Field, Shape)> {
+    if is_bit_vec(&field.ty) {
+        let mut bit = field;
+        bit.ty = parse_quote!(bool);
+        return Some((bit, Shape::Bit));
+    }
+    let stripped = strip_vec(field)?;
+    let element = Shape::from(&stripped.ty);
+    Some((stripped, element))
+}
+
+fn is_bit_vec(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "BitVec")
+}
+
 fn strip_option(field: syn::Field) -> Option<syn::Field> {
     strip_generic(field, "Option")
 }
@@ -234,72 +1020,6 @@ fn strip_generic(field: syn::Field, outer_ident: &str) -> Option<syn::Field> {
     Some(new_field)
 }
 
-fn controls_option(field: &syn::Field) -> Option<Ident> {
-    fn parse(attr: &Attribute) -> Option<Result<Ident, ()>> {
-        let Ok(list) = attr.meta.require_list() else {
-            return Some(Err(()));
-        };
-        let mut tokens = list.tokens.clone().into_iter();
-        match tokens.next() {
-            Some(TokenTree::Ident(ident)) if ident == "presence_of" => (),
-            _ => return None,
-        }
-        match tokens.next() {
-            Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => (),
-            _ => return Some(Err(())),
-        }
-        let Some(TokenTree::Ident(target_field)) = tokens.next() else {
-            return Some(Err(()));
-        };
-        Some(Ok(target_field))
-    }
-
-    let attr = field
-        .attrs
-        .iter()
-        .find(|a| a.path().is_ident("abstract_bits"))?;
-
-    match parse(attr)? {
-        Ok(ident) => Some(ident),
-        Err(_) => abort!(attr.span(), "invalid abstract_bits attribute"; 
-            help = "The syntax is: #[abstract_bits(presence_of = <ident>)] with ident \
-            a later option type field"),
-    }
-}
-
-fn controls_list(field: &syn::Field) -> Option<Ident> {
-    fn parse(attr: &Attribute) -> Result<Ident, ()> {
-        let Ok(list) = attr.meta.require_list() else {
-            return Err(());
-        };
-        let mut tokens = list.tokens.clone().into_iter();
-        match tokens.next() {
-            Some(TokenTree::Ident(ident)) if ident == "length_of" => (),
-            _ => return Err(()),
-        }
-        match tokens.next() {
-            Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => (),
-            _ => return Err(()),
-        }
-        let Some(TokenTree::Ident(target_field)) = tokens.next() else {
-            return Err(());
-        };
-        Ok(target_field)
-    }
-
-    let attr = field
-        .attrs
-        .iter()
-        .find(|a| a.path().is_ident("abstract_bits"))?;
-
-    match parse(attr) {
-        Ok(ident) => Some(ident),
-        Err(_) => abort!(attr.span(), "invalid abstract_bits attribute"; 
-            help = "The syntax is: #[abstract_bits(length_of = <ident>)] with ident \
-            a later option type field"),
-    }
-}
-
 impl Model {
     fn reject_item_generics(generics: &syn::Generics) {
         assert!(generics.lifetimes().count() == 0, "lifetimes not supported");
@@ -314,38 +1034,67 @@ impl Model {
     }
 
     pub(crate) fn from_enum(item: syn::ItemEnum, attr: TokenStream) -> Self {
-        let Ok(bits) = get_num_bits(attr) else {
-            abort!(item.span(), "Every enum must be attributed with its serialized size \
-                in bits."; note = "Example: #[abstract_bits::abstract_bits(bits=2)]");
+        let mut options = Options::from_tokens(attr);
+        let bits = options.take_value("bits").map(require_usize);
+        let is_flags = options.take_flag("flags");
+        let encoding = match (
+            options.take_flag("one_hot"),
+            options.take_flag("thermometer"),
+        ) {
+            (false, false) => Encoding::Binary,
+            (true, false) => Encoding::OneHot,
+            (false, true) => Encoding::Thermometer,
+            (true, true) => abort!(
+                item.ident,
+                "An enum can not be both `one_hot` and \
+                `thermometer` encoded"
+            ),
         };
+        options.reject_remaining();
         Self::reject_item_generics(&item.generics);
+        if is_flags {
+            if encoding != Encoding::Binary {
+                abort!(
+                    item.ident,
+                    "Flags can not be one-hot or thermometer encoded"
+                );
+            }
+            return Self::from_flags(item, bits);
+        }
 
-        let repr = require_repr_attr(&item.attrs, item.span());
-        let variants: Vec<_> = item
-            .variants
-            .clone()
-            .into_iter()
-            .map(|v| EmptyVariant {
-                attrs: v.attrs,
-                ident: v.ident,
-                discriminant: require_usize(
-                    v.discriminant
-                        .clone()
-                        .unwrap_or_else(|| {
-                            abort!(item.span(), "Every enum variant must have an explicit \
-                    discriminant value"; 
-                    note = "Assign a discriminant with = <number>")
-                        })
-                        .1,
-                ),
-            })
-            .collect();
-        verify_all_discriminants_fit(&variants, bits);
+        let mut variants: Vec<Variant> = Vec::new();
+        for variant in item.variants {
+            // variants without a discriminant continue counting from the
+            // last one that matched a value
+            let implicit = variants
+                .iter()
+                .rev()
+                .find_map(|v| next_discriminant(&v.discriminant))
+                .unwrap_or(Discriminant::Value(0));
+            variants.push(Variant::from(variant, implicit));
+        }
+        if variants
+            .iter()
+            .filter(|v| matches!(v.discriminant, Discriminant::Other(_)))
+            .count()
+            > 1
+        {
+            abort!(item.ident, "Only one variant can be marked with `other`");
+        }
+        if encoding != Encoding::Binary {
+            verify_plain_discriminants(&variants);
+        }
+        verify_no_overlap(&variants);
+        let bits = bits.unwrap_or_else(|| infer_bits(&item.ident, &variants, encoding));
+        verify_all_discriminants_fit(&variants, bits, encoding);
+        let repr = find_repr_attr(&item.attrs)
+            .unwrap_or_else(|| smallest_repr(bits, &item.ident));
 
         let ty = Type::Enum {
             bits,
             variants,
             repr_type: repr,
+            encoding,
         };
 
         Self {
@@ -355,29 +1104,79 @@ impl Model {
             ty,
         }
     }
+
+    /// The enum lists the flags, its discriminants are the bit each occupies
+    fn from_flags(item: syn::ItemEnum, bits: Option<usize>) -> Self {
+        let mut flags: Vec<Flag> = Vec::new();
+        for variant in item.variants {
+            Options::from_attrs(&variant.attrs).reject_remaining();
+            if !matches!(variant.fields, syn::Fields::Unit) {
+                abort!(variant.fields, "Flags can not carry fields");
+            }
+            let bit = match variant.discriminant {
+                Some((_, expr)) => require_usize(expr),
+                None => flags.last().map(|f| f.bit + 1).unwrap_or(0),
+            };
+            if let Some(other) = flags.iter().find(|f| f.bit == bit) {
+                abort!(
+                    variant.ident,
+                    "Flag {} occupies the same bit as {}",
+                    variant.ident,
+                    other.name
+                );
+            }
+            let name = screaming_snake_case(&variant.ident.to_string());
+            flags.push(Flag {
+                attrs: options::strip(variant.attrs),
+                ident: Ident::new(&name, variant.ident.span()),
+                name,
+                bit,
+            });
+        }
+
+        let highest = flags.iter().map(|f| f.bit).max();
+        let bits = match (bits, highest) {
+            (Some(bits), Some(highest)) if highest >= bits => abort!(
+                item.ident,
+                "Flag at bit {} does not fit into {} bits",
+                highest,
+                bits
+            ),
+            (Some(bits), _) => bits,
+            (None, Some(highest)) => highest + 1,
+            (None, None) => abort!(item.ident, "The number of bits can not be \
+                inferred without flags"; help = "Specify it: #[abstract_bits(flags, bits = <N>)]"),
+        };
+        let repr = find_repr_attr(&item.attrs)
+            .unwrap_or_else(|| smallest_repr(bits, &item.ident));
+        // the flag set becomes a struct, repr(u8) and the like do not apply
+        let attrs = item
+            .attrs
+            .into_iter()
+            .filter(|a| !a.path().is_ident("repr"))
+            .collect();
+
+        Self {
+            attrs,
+            vis: item.vis,
+            ident: item.ident,
+            ty: Type::Flags {
+                bits,
+                flags,
+                repr_type: repr,
+            },
+        }
+    }
+
     pub(crate) fn from_struct(item: syn::ItemStruct, _attr: TokenStream) -> Self {
         Self::reject_item_generics(&item.generics);
 
-        let is_unit = item
-            .fields
-            .iter()
-            .next()
-            .expect_or_abort("structs without fields are not supported")
-            .ident
-            .is_none();
-        let ty = if is_unit {
-            let field = item.fields.clone().into_iter().next().unwrap_or_else(|| {
-                abort!(item.span(), "Zero sized struct not supported")
-            });
-            Type::UnitStruct(field)
-        } else {
-            let mut fields = Vec::new();
-            for item in item.fields {
-                let field = Field::from(item, &fields);
-                fields.push(field);
+        let ty = match item.fields {
+            syn::Fields::Named(_) => Type::NormalStruct(parse_fields(item.fields)),
+            syn::Fields::Unnamed(_) => Type::TupleStruct(parse_fields(item.fields)),
+            syn::Fields::Unit => {
+                abort!(item.span(), "structs without fields are not supported")
             }
-            check_controlled_fields(&fields);
-            Type::NormalStruct(fields)
         };
 
         Self {
@@ -389,81 +1188,440 @@ impl Model {
     }
 }
 
-fn verify_all_discriminants_fit(variants: &[EmptyVariant], bits: usize) {
-    let biggest = variants
+fn parse_fields(fields: syn::Fields) -> Vec<Field> {
+    let mut parsed = Vec::new();
+    // only fields that end up in the struct definition take up a position
+    let mut position = 0;
+    for field in fields {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(syn::Index {
+                index: position,
+                span: field.ty.span(),
+            }),
+        };
+        let field = Field::from(field, member, &parsed);
+        if field.needed_in_struct_def().is_some() {
+            position += 1;
+        }
+        parsed.push(field);
+    }
+    check_controlled_fields(&parsed);
+    check_trailing_fields(&parsed);
+    resolve_checksums(&mut parsed);
+    parsed
+}
+
+/// Turns the `over` range of every checksum into field indices. Without one a
+/// checksum covers all fields before it.
+fn resolve_checksums(fields: &mut [Field]) {
+    let members: Vec<_> = fields
         .iter()
-        .max_by_key(|var| var.discriminant)
-        .expect("zero size enums are not supported");
-    if biggest.discriminant >= 2usize.pow(bits as u32) {
+        .map(|f| f.needed_in_struct_def().map(|f| f.member))
+        .collect();
+    let index_of = |expr: &syn::Expr| {
+        let member = controlled_member(expr, "over");
+        members
+            .iter()
+            .position(|m| m.as_ref() == Some(&member))
+            .unwrap_or_else(|| {
+                abort!(
+                    expr,
+                    "No field {} to compute the checksum over",
+                    member_name(&member)
+                )
+            })
+    };
+    for (i, field) in fields.iter_mut().enumerate() {
+        let Field::Checksum { covers, over, .. } = field else {
+            continue;
+        };
+        *covers = match over.take() {
+            None => 0..i,
+            Some(range) => {
+                let start = range.start.as_deref().map_or(0, index_of);
+                let end = match (range.end.as_deref(), range.limits) {
+                    (None, _) => members.len(),
+                    (Some(end), syn::RangeLimits::HalfOpen(_)) => index_of(end),
+                    (Some(end), syn::RangeLimits::Closed(_)) => index_of(end) + 1,
+                };
+                if start >= end {
+                    abort!(range, "The checksum covers no fields");
+                }
+                if (start..end).contains(&i) {
+                    abort!(range, "A checksum can not cover itself");
+                }
+                start..end
+            }
+        };
+    }
+}
+
+/// Fields present depending on the remaining input must come last
+fn check_trailing_fields(fields: &[Field]) {
+    if let Some(rest) = fields[..fields.len().saturating_sub(1)]
+        .iter()
+        .find(|f| {
+            matches!(
+                f,
+                Field::List {
+                    length: ListLength::Rest,
+                    ..
+                }
+            )
+        })
+        .and_then(Field::needed_in_struct_def)
+    {
         abort!(
-            biggest.ident.span(),
-            "The discriminant for {} does not fit into {} bits",
-            biggest.ident,
-            bits
+            rest.member.span(),
+            "A list taking up the `rest` of the input must be \
+            the last field"
+        );
+    }
+    let is_trailing = |f: &Field| {
+        matches!(
+            f,
+            Field::Option {
+                presence: Presence::IfRemaining { .. },
+                ..
+            }
+        )
+    };
+    let Some(first) = fields.iter().position(is_trailing) else {
+        return;
+    };
+    if let Some(field) = fields[first..].iter().find(|f| !is_trailing(f)) {
+        let span = match field.needed_in_struct_def() {
+            Some(field) => field.member.span(),
+            None => Span::call_site(),
+        };
+        abort!(span, "Only `if_remaining` fields can follow an `if_remaining` field";
+            note = "Whether they are present depends on the input that remains");
+    }
+}
+
+impl Variant {
+    fn from(variant: syn::Variant, implicit: Discriminant) -> Self {
+        let mut options = Options::from_attrs(&variant.attrs);
+        let discriminant = if options.take_flag("other") {
+            let raw = raw_type(&variant, "other").unwrap_or_else(|| {
+                abort!(variant.span(), "The `other` variant must hold the raw value";
+                    help = "For example: #[abstract_bits(other)] Unknown(u8)")
+            });
+            Discriminant::Other(Box::new(raw))
+        } else if let Some(range) = options.take_value("range") {
+            let (start, end) = require_range(range);
+            Discriminant::Range {
+                start,
+                end,
+                raw: raw_type(&variant, "range").map(Box::new),
+            }
+        } else if let Some((_, expr)) = &variant.discriminant {
+            discriminant_from(expr.clone())
+        } else {
+            implicit
+        };
+        options.reject_remaining();
+
+        let fields = match discriminant {
+            Discriminant::Other(_) | Discriminant::Range { .. } => VariantFields::Unit,
+            Discriminant::Value(_) | Discriminant::Expr { .. } => match variant.fields {
+                syn::Fields::Unit => VariantFields::Unit,
+                syn::Fields::Named(_) => {
+                    VariantFields::Named(parse_fields(variant.fields))
+                }
+                syn::Fields::Unnamed(_) => {
+                    VariantFields::Unnamed(parse_fields(variant.fields))
+                }
+            },
+        };
+        if let VariantFields::Named(fields) | VariantFields::Unnamed(fields) = &fields {
+            if let Some(Field::IsLast(member)) =
+                fields.iter().find(|f| matches!(f, Field::IsLast(_)))
+            {
+                abort!(member.span(), "Only struct fields can be an `is_last` marker";
+                    help = "Move the variant's fields into a struct");
+            }
+            if fields.iter().any(|f| matches!(f, Field::Checksum { .. })) {
+                abort!(variant.ident, "Only structs can have a checksum";
+                    help = "Move the variant's fields into a struct");
+            }
+        }
+
+        Variant {
+            attrs: options::strip(variant.attrs),
+            ident: variant.ident,
+            discriminant,
+            fields,
+        }
+    }
+}
+
+/// Variants matching more then one value can only hold the raw value
+fn raw_type(variant: &syn::Variant, option: &str) -> Option<syn::Type> {
+    if let Some((_, expr)) = &variant.discriminant {
+        abort!(
+            expr,
+            "A variant marked with `{}` can not have a discriminant",
+            option
         );
     }
+    match &variant.fields {
+        syn::Fields::Unit => None,
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            Some(fields.unnamed[0].ty.clone())
+        }
+        _ => abort!(variant.span(), "A variant marked with `{}` can only hold the \
+            raw value", option; help = "For example: Reserved(u8)"),
+    }
+}
+
+/// The lowest and highest value in the range, both inclusive
+fn require_range(expr: syn::Expr) -> (usize, usize) {
+    let syn::Expr::Range(syn::ExprRange {
+        start: Some(start),
+        end: Some(end),
+        limits,
+        ..
+    }) = &expr
+    else {
+        abort!(expr, "Expected a range of discriminants";
+            help = "For example: #[abstract_bits(range = 4..=7)]")
+    };
+    let span = end.span();
+    let (start, end) = (require_usize(*start.clone()), require_usize(*end.clone()));
+    let end = match limits {
+        syn::RangeLimits::Closed(_) => end,
+        syn::RangeLimits::HalfOpen(_) => end
+            .checked_sub(1)
+            .unwrap_or_else(|| abort!(span, "The range can not be empty")),
+    };
+    if end < start {
+        abort!(span, "The range can not be empty");
+    }
+    (start, end)
 }
 
-fn get_num_bits(attr: TokenStream) -> Result<usize, ()> {
-    let mut tokens = attr.into_iter();
-    match tokens.next() {
-        Some(TokenTree::Ident(item)) if item == "bits" => (),
-        _ => return Err(()),
+/// Lowest and highest value each variant matches
+fn matched_values(variant: &Variant) -> Option<(usize, usize)> {
+    match variant.discriminant {
+        Discriminant::Value(value) => Some((value, value)),
+        Discriminant::Range { start, end, .. } => Some((start, end)),
+        Discriminant::Other(_) | Discriminant::Expr { .. } => None,
     }
+}
+
+/// Discriminant of a variant without one following the given discriminant
+fn next_discriminant(previous: &Discriminant) -> Option<Discriminant> {
+    Some(match previous {
+        Discriminant::Value(value) => Discriminant::Value(value + 1),
+        Discriminant::Range { end, .. } => Discriminant::Value(end + 1),
+        Discriminant::Expr { expr, offset } => Discriminant::Expr {
+            expr: expr.clone(),
+            offset: offset + 1,
+        },
+        Discriminant::Other(_) => return None,
+    })
+}
+
+fn discriminant_from(expr: syn::Expr) -> Discriminant {
+    match &expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(_),
+            ..
+        }) => Discriminant::Value(require_usize(expr)),
+        _ => Discriminant::Expr {
+            expr: Box::new(expr),
+            offset: 0,
+        },
+    }
+}
 
-    match tokens.next() {
-        Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => (),
-        _ => return Err(()),
+/// One-hot and thermometer codes have no room for ranges and other values
+fn verify_plain_discriminants(variants: &[Variant]) {
+    for variant in variants {
+        if !matches!(variant.discriminant, Discriminant::Value(_)) {
+            abort!(
+                variant.ident,
+                "One-hot and thermometer encoded enums only \
+                support literal or implicit discriminants"
+            );
+        }
     }
+}
 
-    let Some(TokenTree::Literal(num)) = tokens.next() else {
-        return Err(());
+/// Enough bits to store the largest discriminant
+fn infer_bits(ident: &Ident, variants: &[Variant], encoding: Encoding) -> usize {
+    for variant in variants {
+        match variant.discriminant {
+            Discriminant::Expr { .. } => {
+                abort!(variant.ident, "The number of bits can not \
+                be inferred from a discriminant that is not a literal";
+                help = "Specify it: #[abstract_bits(bits = <N>)]")
+            }
+            Discriminant::Other(_) => abort!(variant.ident, "The number of bits can not \
+                be inferred for an enum with an `other` variant";
+                help = "Specify it: #[abstract_bits(bits = <N>)]"),
+            Discriminant::Value(_) | Discriminant::Range { .. } => (),
+        }
+    }
+    let largest = variants
+        .iter()
+        .filter_map(matched_values)
+        .map(|(_, end)| end)
+        .max();
+    let Some(largest) = largest else {
+        abort!(ident, "An enum without variants can not be serialized");
     };
+    encoding.bits_needed(largest)
+}
 
-    num.to_string().parse().map_err(|_| ())
+fn verify_no_overlap(variants: &[Variant]) {
+    let mut matched: Vec<_> = variants
+        .iter()
+        .filter_map(|var| Some((matched_values(var)?, &var.ident)))
+        .collect();
+    matched.sort_by_key(|((start, _), _)| *start);
+    for pair in matched.windows(2) {
+        let [((_, end), _), ((start, _), ident)] = pair else {
+            unreachable!("windows are always of size 2");
+        };
+        if start <= end {
+            abort!(
+                ident,
+                "The discriminant of {} overlaps with another variant",
+                ident
+            );
+        }
+    }
 }
 
-fn require_repr_attr(attrs: &[Attribute], span: Span) -> Ident {
-    let attr = attrs
+fn verify_all_discriminants_fit(variants: &[Variant], bits: usize, encoding: Encoding) {
+    let Some((biggest, discriminant)) = variants
         .iter()
-        .find(|a| a.path().is_ident("repr"))
-        .unwrap_or_else(|| abort!(span, "enum must have repr attribute"));
+        .filter_map(|var| Some((var, matched_values(var)?.1)))
+        .max_by_key(|(_, discriminant)| *discriminant)
+    else {
+        return;
+    };
+    if discriminant as u64 > encoding.max_discriminant(bits) {
+        abort!(
+            biggest.ident.span(),
+            "The discriminant for {} does not fit into {} bits",
+            biggest.ident,
+            bits
+        );
+    }
+}
+
+/// `LowPower` becomes `LOW_POWER` and `HTTPServer` becomes `HTTP_SERVER`
+fn screaming_snake_case(camel: &str) -> String {
+    let chars: Vec<char> = camel.chars().collect();
+    let mut screaming = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let word_start = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if word_start {
+            screaming.push('_');
+        }
+        screaming.extend(c.to_uppercase());
+    }
+    screaming
+}
+
+fn find_repr_attr(attrs: &[Attribute]) -> Option<Ident> {
+    let attr = attrs.iter().find(|a| a.path().is_ident("repr"))?;
 
     let list = attr
         .meta
         .require_list()
-        .expect("we just found an attribute therefore its non empty");
+        .unwrap_or_else(|_| abort!(attr, "repr attribute should be a list"));
 
     let Some(TokenTree::Ident(repr_type)) = list.tokens.clone().into_iter().next() else {
-        abort!(span, "repr attribute on enum should contain repr type");
+        abort!(attr, "repr attribute on enum should contain repr type");
     };
 
-    repr_type
+    Some(repr_type)
+}
+
+/// The smallest unsigned primitive that can hold every discriminant
+fn smallest_repr(bits: usize, ident: &Ident) -> Ident {
+    let name = match bits {
+        1..=8 => "u8",
+        9..=16 => "u16",
+        17..=32 => "u32",
+        33..=64 => "u64",
+        _ => abort!(ident, "Enums can be at most 64 bits"),
+    };
+    Ident::new(name, ident.span())
 }
 
 fn require_usize(expr: syn::Expr) -> usize {
     if let syn::Expr::Lit(syn::ExprLit {
         lit: syn::Lit::Int(d),
         ..
-    }) = expr
+    }) = &expr
     {
         d.base10_parse()
-            .expect("only valid numbers can be enum discriminant")
+            .unwrap_or_else(|e| abort!(expr, "Expected an unsigned number: {}", e))
     } else {
-        unreachable!("only digits form a valid enum discriminant expression")
+        abort!(expr, "Expected an unsigned number literal")
     }
 }
 
 fn check_controlled_fields(fields: &[Field]) {
-    for field in fields {
-        if let Field::ControlOption(controlled) = field {
+    for (i, field) in fields.iter().enumerate() {
+        if let Some(option) = field.controlled_option() {
+            if !fields[..i]
+                .iter()
+                .any(|f| f.controls_option(&option.member))
+            {
+                abort!(option.member.span(), "Option {} has nothing deciding whether it is \
+                    present", member_name(&option.member); help = "Add a \
+                    #[abstract_bits(presence_of = {})] bool before it or give it a \
+                    condition with #[abstract_bits(if = <expr>)]", member_name(&option.member))
+            }
+            if fields[..i]
+                .iter()
+                .filter(|f| f.controls_option(&option.member))
+                .count()
+                > 1
+            {
+                abort!(option.member.span(), "Option {} has more than one field deciding \
+                    whether it is present", member_name(&option.member); note = "List it \
+                    either in a `presence_bitmap` or in a `presence_of`/`absence_of`, not \
+                    both.")
+            }
+        }
+        if let Field::ControlTag { controlled, .. } = field {
+            if !fields
+                .iter()
+                .any(|f| matches!(f, Field::Tagged(f) if f.member == *controlled))
+            {
+                abort!(controlled.span(), "No field {} to be controlled by this annotated \
+                    field", member_name(controlled); note = "The enum whose tag this is \
+                    must follow the field storing the tag.")
+            }
+        }
+        let controlled_options = match field {
+            Field::ControlOption { controlled, .. } => std::slice::from_ref(controlled),
+            Field::PresenceBitmap { controlled, .. } => controlled.as_slice(),
+            _ => &[],
+        };
+        for controlled in controlled_options {
             if !fields
                 .iter()
-                .filter_map(Field::option_stripped)
-                .any(|f| f.ident == *controlled)
+                .filter_map(Field::controlled_option)
+                .any(|f| f.member == *controlled)
             {
                 abort!(controlled.span(), "No field {} to be controlled by this annotated \
-                    field", controlled; note = "The field being controlled must follow \
+                    field", member_name(controlled); note = "The field being controlled must follow \
                     the boolean (bitfield) controlling it.")
             }
         }
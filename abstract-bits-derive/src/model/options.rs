@@ -0,0 +1,98 @@
+use proc_macro_error2::abort;
+use proc_macro2::TokenStream;
+use syn::ext::IdentExt;
+use syn::parse::{ParseStream, Parser};
+use syn::spanned::Spanned;
+use syn::{Attribute, Expr, Ident, Meta, MetaNameValue, Token};
+
+/// Everything passed through `#[abstract_bits(..)]` attributes on a field or
+/// as arguments to the macro itself.
+/// Options are taken out by the code that understands them, whatever remains
+/// was not understood.
+#[derive(Debug)]
+pub struct Options {
+    metas: Vec<Meta>,
+}
+
+impl Options {
+    pub fn from_attrs(attrs: &[Attribute]) -> Self {
+        let metas = attrs
+            .iter()
+            .filter(|a| a.path().is_ident("abstract_bits"))
+            .flat_map(|attr| {
+                attr.parse_args_with(parse_metas).unwrap_or_else(|e| {
+                    abort!(attr.span(), "invalid abstract_bits attribute: {}", e)
+                })
+            })
+            .collect();
+        Self { metas }
+    }
+
+    /// The arguments of the macro itself, as in `#[abstract_bits(bits = 2)]`
+    pub fn from_tokens(tokens: TokenStream) -> Self {
+        let metas = parse_metas.parse2(tokens).unwrap_or_else(|e| {
+            abort!(e.span(), "invalid abstract_bits arguments: {}", e)
+        });
+        Self { metas }
+    }
+
+    /// Takes out an option in the form `key = <expr>`
+    pub fn take_value(&mut self, key: &str) -> Option<Expr> {
+        let pos = self.metas.iter().position(|m| m.path().is_ident(key))?;
+        match self.metas.remove(pos) {
+            Meta::NameValue(name_value) => Some(name_value.value),
+            other => abort!(other.span(), "expected a value for `{}`", key;
+                help = "The syntax is: #[abstract_bits({} = <value>)]", key),
+        }
+    }
+
+    /// Takes out an option without a value, returns whether it was present
+    pub fn take_flag(&mut self, key: &str) -> bool {
+        let Some(pos) = self.metas.iter().position(|m| m.path().is_ident(key)) else {
+            return false;
+        };
+        match self.metas.remove(pos) {
+            Meta::Path(_) => true,
+            other => abort!(other.span(), "`{}` does not take a value", key;
+                help = "The syntax is: #[abstract_bits({})]", key),
+        }
+    }
+
+    pub fn reject_remaining(self) {
+        if let Some(meta) = self.metas.first() {
+            abort!(meta.span(), "unknown or misplaced abstract_bits option")
+        }
+    }
+}
+
+/// Like a comma separated list of [`Meta`] except keywords such as `if` are
+/// allowed as option names
+fn parse_metas(input: ParseStream) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    while !input.is_empty() {
+        let path = syn::Path::from(input.call(Ident::parse_any)?);
+        let meta = if input.peek(Token![=]) {
+            Meta::NameValue(MetaNameValue {
+                path,
+                eq_token: input.parse()?,
+                value: input.parse()?,
+            })
+        } else {
+            Meta::Path(path)
+        };
+        metas.push(meta);
+        if input.is_empty() {
+            break;
+        }
+        input.parse::<Token![,]>()?;
+    }
+    Ok(metas)
+}
+
+/// Attributes meant for the macro should not end up in the generated code
+pub fn strip(attrs: Vec<Attribute>) -> Vec<Attribute> {
+    attrs
+        .into_iter()
+        .filter(|a| !a.path().is_ident("abstract_bits"))
+        .collect()
+}
@@ -0,0 +1,95 @@
+/// Algorithm for fields annotated with `#[abstract_bits(checksum = ...)]`.
+/// The field stores the low bits of the result that fit in it.
+///
+/// # Example
+/// ```
+/// # use abstract_bits::{abstract_bits, AbstractBits, Checksum};
+/// struct Complement;
+///
+/// impl Checksum for Complement {
+///     fn checksum(bytes: &[u8]) -> u64 {
+///         let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+///         sum.wrapping_neg() as u64
+///     }
+/// }
+///
+/// #[abstract_bits]
+/// #[derive(Debug, PartialEq, Eq)]
+/// struct Header {
+///     kind: u8,
+///     len: u8,
+///     #[abstract_bits(checksum = Complement)]
+///     reserved: u8,
+/// }
+///
+/// let header = Header { kind: 1, len: 2 };
+/// assert_eq!(header.to_abstract_bits().unwrap(), [1, 2, 0xfd]);
+/// ```
+pub trait Checksum {
+    /// `bytes` holds the covered bits, the last byte padded with zeros
+    fn checksum(bytes: &[u8]) -> u64;
+}
+
+/// CRC-16/KERMIT as used by IEEE 802.15.4 and Zigbee, use
+/// `checksum = crc16_kermit`. Polynomial 0x1021 reflected, initial value 0, no
+/// final xor. The check value for `b"123456789"` is 0x2189.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Crc16Kermit;
+
+impl Checksum for Crc16Kermit {
+    fn checksum(bytes: &[u8]) -> u64 {
+        let mut crc: u16 = 0;
+        for byte in bytes {
+            crc ^= *byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0x8408
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        crc as u64
+    }
+}
+
+/// The CRC-32 of Ethernet and zip, use `checksum = crc32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn checksum(bytes: &[u8]) -> u64 {
+        let mut crc: u32 = 0xffff_ffff;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc as u64
+    }
+}
+
+/// Sum of the bytes, use `checksum = sum8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sum8;
+
+impl Checksum for Sum8 {
+    fn checksum(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) as u64
+    }
+}
+
+/// Xor of the bytes, use `checksum = xor8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Xor8;
+
+impl Checksum for Xor8 {
+    fn checksum(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0u8, |xor, b| xor ^ b) as u64
+    }
+}
@@ -4,6 +4,13 @@ use crate::{BufferTooSmall, UnexpectedEndOfBits};
 pub enum ReadErrorCause {
     #[error("Got invalid discriminant {got} while deserializing enum {ty}")]
     InvalidDiscriminant { ty: &'static str, got: usize },
+    #[error("Expected exactly one bit set while deserializing enum {ty}, got {got:#b}")]
+    NotOneHot { ty: &'static str, got: u64 },
+    #[error(
+        "Expected only consecutive low bits set while deserializing enum {ty}, \
+        got {got:#b}"
+    )]
+    NotThermometer { ty: &'static str, got: u64 },
     #[error("Could not deserialize primitive while deserializing {ty}")]
     NotEnoughInput {
         ty: &'static str,
@@ -53,6 +60,13 @@ pub enum FromBytesError {
         #[source]
         cause: ReadErrorCause,
     },
+    #[error("Could not read tag for enum {field_name} in struct {struct_name}")]
+    ReadTag {
+        field_name: &'static str,
+        struct_name: &'static str,
+        #[source]
+        cause: ReadErrorCause,
+    },
     #[error("Could not read length for list {field_name} in struct {struct_name}")]
     ReadListLength {
         field_name: &'static str,
@@ -60,6 +74,15 @@ pub enum FromBytesError {
         #[source]
         cause: ReadErrorCause,
     },
+    #[error(
+        "Stored length {raw} of list {field_name} in struct {struct_name} does \
+        not describe a valid length"
+    )]
+    InvalidListLength {
+        field_name: &'static str,
+        struct_name: &'static str,
+        raw: u64,
+    },
     #[error(
         "Could not read {list_len} items into list {field_name} 
         in struct {struct_name}"
@@ -88,6 +111,40 @@ pub enum FromBytesError {
         #[source]
         cause: ReadErrorCause,
     },
+    #[error(
+        "{n_bits} bits left after reading list {field_name} in struct \
+        {struct_name}, too few to form another element"
+    )]
+    LeftoverBits {
+        field_name: &'static str,
+        struct_name: &'static str,
+        n_bits: usize,
+    },
+    #[error(
+        "Value of the entry with tag {tag} in {ty} is {len_bits} bits long \
+        but only {read_bits} bits were read"
+    )]
+    TlvLengthMismatch {
+        ty: &'static str,
+        tag: usize,
+        len_bits: usize,
+        read_bits: usize,
+    },
+    #[error(
+        "Checksum of {struct_name} does not match, expected {expected:#x} \
+        but found {found:#x}"
+    )]
+    ChecksumMismatch {
+        struct_name: &'static str,
+        expected: u64,
+        found: u64,
+    },
+    #[error("Expected magic value {expected:#x} in {struct_name}, found {found:#x}")]
+    BadMagic {
+        struct_name: &'static str,
+        expected: u64,
+        found: u64,
+    },
     #[error(transparent)]
     ReadPrimitive(ReadErrorCause),
 }
@@ -156,6 +213,17 @@ impl FromBytesError {
             self
         }
     }
+    pub fn read_tag(self, struct_name: &'static str, field_name: &'static str) -> Self {
+        if let Self::ReadPrimitive(cause) = self {
+            Self::ReadTag {
+                field_name,
+                struct_name,
+                cause,
+            }
+        } else {
+            self
+        }
+    }
     pub fn read_list(
         self,
         struct_name: &'static str,
@@ -196,6 +264,95 @@ impl FromBytesError {
 pub enum ToBytesError {
     #[error("List too long to fit. Max length {max}, got: {got}")]
     ListTooLong { max: usize, got: usize },
+    #[error(
+        "Value {got} of {field_name} in {struct_name} does not fit. \
+        Max value is {max}"
+    )]
+    ValueTooLarge {
+        field_name: &'static str,
+        struct_name: &'static str,
+        max: u64,
+        got: u64,
+    },
+    #[error(
+        "Value {got} of {field_name} in {struct_name} is out of range. \
+        It must be in {min}..={max}"
+    )]
+    ValueOutOfRange {
+        field_name: &'static str,
+        struct_name: &'static str,
+        min: u64,
+        max: u64,
+        got: u64,
+    },
+    #[error(
+        "Option {field_name} in {struct_name} does not match its condition, \
+        it should be {}", some_or_none(*.expected_some)
+    )]
+    PresenceMismatch {
+        field_name: &'static str,
+        struct_name: &'static str,
+        expected_some: bool,
+    },
+    #[error(
+        "{field_name} in {struct_name} is Some with the value that is reserved \
+        to mean None"
+    )]
+    ReservedNoneValue {
+        field_name: &'static str,
+        struct_name: &'static str,
+    },
+    #[error(
+        "{field_name} in {struct_name} holds {got}, the discriminant of another \
+        variant"
+    )]
+    KnownDiscriminant {
+        field_name: &'static str,
+        struct_name: &'static str,
+        got: u64,
+    },
+    #[error(
+        "{field_name} in {struct_name} does not match {controlled}, the field \
+        it controls"
+    )]
+    ControllerMismatch {
+        field_name: &'static str,
+        controlled: &'static str,
+        struct_name: &'static str,
+    },
+    #[error(
+        "Lists {field_name} and {other_field} in {struct_name} share their \
+        length but are {len} and {other_len} long"
+    )]
+    ListLengthMismatch {
+        field_name: &'static str,
+        other_field: &'static str,
+        struct_name: &'static str,
+        len: usize,
+        other_len: usize,
+    },
+    #[error(
+        "Length {len} of list {field_name} in {struct_name} can not be stored \
+        exactly by its length field"
+    )]
+    LengthNotEncodable {
+        field_name: &'static str,
+        struct_name: &'static str,
+        len: usize,
+    },
+    #[error("List {field_name} in {struct_name} contains its terminator")]
+    TerminatorInList {
+        field_name: &'static str,
+        struct_name: &'static str,
+    },
+    #[error(
+        "List {field_name} in {struct_name} is empty, it needs an element \
+        to mark as last"
+    )]
+    EmptyList {
+        field_name: &'static str,
+        struct_name: &'static str,
+    },
     #[error("Buffer is too small to serialize {ty} into")]
     BufferTooSmall {
         ty: &'static str,
@@ -209,3 +366,7 @@ pub enum ToBytesError {
         cause: BufferTooSmall,
     },
 }
+
+fn some_or_none(is_some: bool) -> &'static str {
+    if is_some { "Some" } else { "None" }
+}
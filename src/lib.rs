@@ -1,16 +1,38 @@
 #![doc = include_str!("../README.md")]
 
 pub use abstract_bits_derive::abstract_bits;
-pub use arbitrary_int::{u1, u2, u3, u4, u5, u6, u7};
+pub use arbitrary_int::{
+    u1, u2, u3, u4, u5, u6, u7, u9, u10, u11, u12, u13, u14, u15, u17, u18, u19, u20,
+    u21, u22, u23, u24, u25, u26, u27, u28, u29, u30, u31, u33, u34, u35, u36, u37, u38,
+    u39, u40, u41, u42, u43, u44, u45, u46, u47, u48, u49, u50, u51, u52, u53, u54, u55,
+    u56, u57, u58, u59, u60, u61, u62, u63,
+};
+#[cfg(feature = "bitflags")]
+pub use bitflags;
 pub use bitvec;
-use bitvec::order::Lsb0;
+use core::ops::Range;
+
+use bitvec::array::BitArray;
+use bitvec::order::{BitOrder, Lsb0};
 use bitvec::slice::BitSlice;
+use bitvec::view::BitViewSized;
 
+mod checksum;
+pub use checksum::{Checksum, Crc16Kermit, Crc32, Sum8, Xor8};
 mod error;
 pub use error::{FromBytesError, ReadErrorCause, ToBytesError};
+mod prefixed;
+pub use prefixed::{LengthPrefix, Prefixed};
+mod tlv;
+pub use tlv::{Bits, Bytes, LengthUnit, Tlv, TlvEntry, TlvTag};
+
+/// Buffer [`AbstractBits::to_abstract_bits`] starts with for large types
+const INITIAL_BUFFER_SIZE: usize = 1024;
 
 pub trait AbstractBits {
     const MIN_BITS: usize;
+    /// `usize::MAX` for types without an upper bound, such as a struct ending
+    /// in a list marked `#[abstract_bits(rest)]`
     const MAX_BITS: usize;
     /// To get the amount written use [`BitWriter::bits_written`]
     /// or [`BitWriter::bytes_written`]
@@ -21,14 +43,28 @@ pub trait AbstractBits {
     where
         Self: Sized;
 
+    /// Types without an upper bound on their size, see [`AbstractBits::MAX_BITS`],
+    /// start with a small buffer that grows until the value fits
     fn to_abstract_bits(&self) -> Result<Vec<u8>, ToBytesError> {
-        let needed_bytes = Self::MAX_BITS.div_ceil(8);
-        let mut buffer = vec![0u8; needed_bytes];
-        let mut writer = BitWriter::from(buffer.as_mut_slice());
-        self.write_abstract_bits(&mut writer)?;
-        let bytes = writer.bytes_written();
-        buffer.truncate(bytes);
-        Ok(buffer)
+        let max_bytes = Self::MAX_BITS.div_ceil(8);
+        let mut buffer_size = max_bytes.min(INITIAL_BUFFER_SIZE);
+        loop {
+            let mut buffer = vec![0u8; buffer_size];
+            let mut writer = BitWriter::from(buffer.as_mut_slice());
+            match self.write_abstract_bits(&mut writer) {
+                Ok(()) => {
+                    let bytes = writer.bytes_written();
+                    buffer.truncate(bytes);
+                    return Ok(buffer);
+                }
+                Err(
+                    ToBytesError::BufferTooSmall { .. } | ToBytesError::AddPadding { .. },
+                ) if buffer_size < max_bytes => {
+                    buffer_size = buffer_size.saturating_mul(2).min(max_bytes);
+                }
+                Err(other) => return Err(other),
+            }
+        }
     }
 
     fn from_abstract_bits(bytes: &[u8]) -> Result<Self, FromBytesError>
@@ -40,6 +76,40 @@ pub trait AbstractBits {
     }
 }
 
+/// Splits an enum into its discriminant (the tag) and the fields of the
+/// variant (the body). Implemented by the [`abstract_bits`] macro for every
+/// enum. Used for fields whose tag is stored in an earlier field annotated with
+/// `#[abstract_bits(tag_of = <field_name>)]`.
+pub trait Tagged {
+    const MIN_BODY_BITS: usize;
+    const MAX_BODY_BITS: usize;
+    fn tag(&self) -> usize;
+    fn write_body(&self, writer: &mut BitWriter) -> Result<(), ToBytesError>;
+    /// Errors with [`ReadErrorCause::InvalidDiscriminant`] if no variant
+    /// matches the tag
+    fn read_body(tag: usize, reader: &mut BitReader) -> Result<Self, FromBytesError>
+    where
+        Self: Sized;
+    /// Whether [`read_body`](Tagged::read_body) has a variant for the tag
+    fn has_variant(tag: usize) -> bool;
+}
+
+/// Structs carrying a flag that marks the last element of a list. Implemented
+/// by the [`abstract_bits`] macro for structs with a field annotated with
+/// `#[abstract_bits(is_last)]`. Used for lists annotated with
+/// `#[abstract_bits(until_last)]`.
+pub trait LastMarked {
+    fn write_marked(
+        &self,
+        is_last: bool,
+        writer: &mut BitWriter,
+    ) -> Result<(), ToBytesError>;
+    /// Returns whether the element read is marked as last
+    fn read_marked(reader: &mut BitReader) -> Result<(Self, bool), FromBytesError>
+    where
+        Self: Sized;
+}
+
 macro_rules! impl_abstract_bits_for_UInt {
     ($base_type:ty, $write_method:ident, $read_method: ident) => {
         impl<const N: usize> AbstractBits for arbitrary_int::UInt<$base_type, N> {
@@ -148,7 +218,7 @@ impl AbstractBits for bool {
 
 impl<const N: usize, T: AbstractBits + Sized> AbstractBits for [T; N] {
     const MIN_BITS: usize = T::MIN_BITS * N;
-    const MAX_BITS: usize = T::MAX_BITS * N;
+    const MAX_BITS: usize = T::MAX_BITS.saturating_mul(N);
 
     fn write_abstract_bits(&self, writer: &mut BitWriter) -> Result<(), ToBytesError> {
         for element in self.iter() {
@@ -170,6 +240,133 @@ impl<const N: usize, T: AbstractBits + Sized> AbstractBits for [T; N] {
     }
 }
 
+/// Reads `N` elements using `read_element`. Used by the [`abstract_bits`] macro
+/// for arrays of integers that are stored in a wider primitive.
+#[doc(hidden)]
+pub fn read_array<'a, T, const N: usize>(
+    reader: &mut BitReader<'a>,
+    mut read_element: impl FnMut(&mut BitReader<'a>) -> Result<T, FromBytesError>,
+) -> Result<[T; N], FromBytesError> {
+    let mut res = Vec::with_capacity(N);
+    for _ in 0..N {
+        res.push(read_element(reader)?);
+    }
+    res.try_into()
+        .map_err(|_| unreachable!("for loop ensures vec length matches array's"))
+}
+
+macro_rules! impl_abstract_bits_for_tuple {
+    ($($element:ident $idx:tt),+) => {
+        impl<$($element: AbstractBits),+> AbstractBits for ($($element,)+) {
+            const MIN_BITS: usize = 0 $(+ $element::MIN_BITS)+;
+            const MAX_BITS: usize = {
+                let max = 0usize;
+                $(let max = max.saturating_add($element::MAX_BITS);)+
+                max
+            };
+
+            fn write_abstract_bits(
+                &self,
+                writer: &mut BitWriter,
+            ) -> Result<(), ToBytesError> {
+                $(self.$idx.write_abstract_bits(writer)?;)+
+                Ok(())
+            }
+
+            fn read_abstract_bits(reader: &mut BitReader) -> Result<Self, FromBytesError>
+            where
+                Self: Sized,
+            {
+                Ok(($($element::read_abstract_bits(reader)?,)+))
+            }
+        }
+    };
+}
+
+impl_abstract_bits_for_tuple! {A 0}
+impl_abstract_bits_for_tuple! {A 0, B 1}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10}
+impl_abstract_bits_for_tuple! {A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11}
+
+/// Stored as its bits in order, a `BitArray<[u8; 8]>` takes up 64 bits. For a
+/// bitmap whose length is set by a controller use a `BitVec` field.
+impl<A: BitViewSized, O: BitOrder> AbstractBits for BitArray<A, O> {
+    const MIN_BITS: usize = bitvec::mem::bits_of::<A>();
+    const MAX_BITS: usize = bitvec::mem::bits_of::<A>();
+
+    fn write_abstract_bits(&self, writer: &mut BitWriter) -> Result<(), ToBytesError> {
+        for bit in self.iter().by_vals() {
+            bit.write_abstract_bits(writer)?;
+        }
+        Ok(())
+    }
+    fn read_abstract_bits(reader: &mut BitReader) -> Result<Self, FromBytesError>
+    where
+        Self: Sized,
+    {
+        let mut res = Self::ZERO;
+        for mut bit in res.iter_mut() {
+            *bit = bool::read_abstract_bits(reader)?;
+        }
+        Ok(res)
+    }
+}
+
+/// Implements [`AbstractBits`] for types generated by the
+/// [`bitflags`](https://docs.rs/bitflags) crate. They occupy as many bits as
+/// their underlying integer and bits without a flag are kept.
+///
+/// # Example
+/// ```
+/// # use abstract_bits::AbstractBits;
+/// abstract_bits::bitflags::bitflags! {
+///     #[derive(Debug, PartialEq, Eq)]
+///     struct Capabilities: u8 {
+///         const WAKEUP = 1;
+///         const ROUTING = 1 << 3;
+///     }
+/// }
+/// abstract_bits::impl_abstract_bits_for_bitflags!(Capabilities);
+///
+/// let capabilities = Capabilities::from_abstract_bits(&[0b1000_1001]).unwrap();
+/// assert_eq!(capabilities.bits(), 0b1000_1001);
+/// ```
+#[cfg(feature = "bitflags")]
+#[macro_export]
+macro_rules! impl_abstract_bits_for_bitflags {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl $crate::AbstractBits for $ty {
+            const MIN_BITS: usize =
+                <<$ty as $crate::bitflags::Flags>::Bits as $crate::AbstractBits>::MIN_BITS;
+            const MAX_BITS: usize =
+                <<$ty as $crate::bitflags::Flags>::Bits as $crate::AbstractBits>::MAX_BITS;
+
+            fn write_abstract_bits(
+                &self,
+                writer: &mut $crate::BitWriter,
+            ) -> Result<(), $crate::ToBytesError> {
+                let bits = $crate::bitflags::Flags::bits(self);
+                $crate::AbstractBits::write_abstract_bits(&bits, writer)
+            }
+
+            fn read_abstract_bits(
+                reader: &mut $crate::BitReader,
+            ) -> Result<Self, $crate::FromBytesError> {
+                let bits = $crate::AbstractBits::read_abstract_bits(reader)?;
+                Ok($crate::bitflags::Flags::from_bits_retain(bits))
+            }
+        }
+    )+};
+}
+
 pub struct BitReader<'a> {
     pos: usize,
     buf: &'a BitSlice<u8, Lsb0>,
@@ -213,6 +410,16 @@ impl BitReader<'_> {
     pub fn bytes_read(&self) -> usize {
         self.pos.div_ceil(8)
     }
+    /// Bits left in the input, including the padding of the last byte
+    pub fn bits_remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+    /// The bits in `range`, counted from the start of the input, packed into
+    /// bytes. Used by the [`abstract_bits`] macro for checksums.
+    #[doc(hidden)]
+    pub fn bytes_in(&self, range: Range<usize>) -> Vec<u8> {
+        bytes_in(&self.buf[range])
+    }
     pub fn skip(&mut self, n_bits: usize) -> Result<(), UnexpectedEndOfBits> {
         if self.pos + n_bits > self.buf.len() {
             Err(UnexpectedEndOfBits {
@@ -281,7 +488,7 @@ macro_rules! write_primitive {
             if self.pos + n_bits > self.buf.len() {
                 Err(BufferTooSmall {
                     n_bits,
-                    bits_needed: self.buf.len() - (self.pos + n_bits),
+                    bits_needed: (self.pos + n_bits) - self.buf.len(),
                 })
             } else {
                 self.buf[self.pos..self.pos + n_bits].copy_from_bitslice(&val[..n_bits]);
@@ -300,6 +507,26 @@ impl BitWriter<'_> {
     pub fn bytes_written(&self) -> usize {
         self.pos.div_ceil(8)
     }
+    /// The bits in `range`, counted from the start of the output, packed into
+    /// bytes. Used by the [`abstract_bits`] macro for checksums.
+    #[doc(hidden)]
+    pub fn bytes_in(&self, range: Range<usize>) -> Vec<u8> {
+        bytes_in(&self.buf[range])
+    }
+    /// Writes `value` over earlier output starting at bit `pos`. Used by the
+    /// [`abstract_bits`] macro to fill in checksums.
+    #[doc(hidden)]
+    pub fn write_at(
+        &mut self,
+        pos: usize,
+        value: &impl AbstractBits,
+    ) -> Result<(), ToBytesError> {
+        let end = self.pos;
+        self.pos = pos;
+        let res = value.write_abstract_bits(self);
+        self.pos = end;
+        res
+    }
     pub fn skip(&mut self, n_bits: usize) -> Result<(), BufferTooSmall> {
         if self.pos + n_bits > self.buf.len() {
             Err(BufferTooSmall {
@@ -330,6 +557,13 @@ impl BitWriter<'_> {
     write_primitive!(write_u64, u64);
 }
 
+fn bytes_in(bits: &BitSlice<u8, Lsb0>) -> Vec<u8> {
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+    BitSlice::<u8, Lsb0>::from_slice_mut(&mut bytes)[..bits.len()]
+        .copy_from_bitslice(bits);
+    bytes
+}
+
 impl<'a> From<&'a mut [u8]> for BitWriter<'a> {
     fn from(buf: &'a mut [u8]) -> Self {
         Self {
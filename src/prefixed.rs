@@ -0,0 +1,136 @@
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::{AbstractBits, BitReader, BitWriter, FromBytesError, ToBytesError};
+
+/// Integers that can store the length of a list in front of it. Used by
+/// [`Prefixed`] and by fields annotated with `#[abstract_bits(len_prefix = u8)]`.
+pub trait LengthPrefix: AbstractBits + Sized {
+    /// Longest list the prefix can describe
+    const MAX_LEN: usize;
+    /// `None` if the length does not fit
+    fn from_len(len: usize) -> Option<Self>;
+    fn to_len(&self) -> usize;
+}
+
+macro_rules! impl_length_prefix_for_core_int {
+    ($($type:ty),+) => {$(
+        impl LengthPrefix for $type {
+            const MAX_LEN: usize = if <$type>::MAX as u128 > usize::MAX as u128 {
+                usize::MAX
+            } else {
+                <$type>::MAX as usize
+            };
+
+            fn from_len(len: usize) -> Option<Self> {
+                len.try_into().ok()
+            }
+            fn to_len(&self) -> usize {
+                *self as usize
+            }
+        }
+    )+};
+}
+
+impl_length_prefix_for_core_int! {u8, u16, u32, u64}
+
+macro_rules! impl_length_prefix_for_UInt {
+    ($($base_type:ty),+) => {$(
+        impl<const N: usize> LengthPrefix for arbitrary_int::UInt<$base_type, N> {
+            const MAX_LEN: usize = if N >= usize::BITS as usize {
+                usize::MAX
+            } else {
+                (1 << N) - 1
+            };
+
+            fn from_len(len: usize) -> Option<Self> {
+                Self::try_new(len.try_into().ok()?).ok()
+            }
+            fn to_len(&self) -> usize {
+                self.value() as usize
+            }
+        }
+    )+};
+}
+
+impl_length_prefix_for_UInt! {u8, u16, u32, u64}
+
+/// A list preceded by its length stored as an `L`. Unlike a `Vec` field it
+/// needs no controller so it can be nested: `Prefixed<u8, Prefixed<u4, u8>>`.
+///
+/// # Example
+/// ```
+/// # use abstract_bits::{AbstractBits, Prefixed};
+/// let names: Prefixed<u8, Prefixed<u8, u8>> =
+///     Prefixed::from(vec![Prefixed::from(b"ab".to_vec()), Prefixed::from(vec![])]);
+/// let bytes = names.to_abstract_bits().unwrap();
+/// assert_eq!(bytes, [2, 2, b'a', b'b', 0]);
+/// assert_eq!(Prefixed::from_abstract_bits(&bytes).unwrap(), names);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Prefixed<L, T> {
+    items: Vec<T>,
+    prefix: PhantomData<fn() -> L>,
+}
+
+impl<L, T> Prefixed<L, T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl<L, T> Default for Prefixed<L, T> {
+    fn default() -> Self {
+        Self::from(Vec::new())
+    }
+}
+
+impl<L, T> From<Vec<T>> for Prefixed<L, T> {
+    fn from(items: Vec<T>) -> Self {
+        Self {
+            items,
+            prefix: PhantomData,
+        }
+    }
+}
+
+impl<L, T> Deref for Prefixed<L, T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<L, T> DerefMut for Prefixed<L, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+impl<L: LengthPrefix, T: AbstractBits> AbstractBits for Prefixed<L, T> {
+    const MIN_BITS: usize = L::MIN_BITS;
+    const MAX_BITS: usize =
+        L::MAX_BITS.saturating_add(L::MAX_LEN.saturating_mul(T::MAX_BITS));
+
+    fn write_abstract_bits(&self, writer: &mut BitWriter) -> Result<(), ToBytesError> {
+        let len = L::from_len(self.items.len()).ok_or(ToBytesError::ListTooLong {
+            max: L::MAX_LEN,
+            got: self.items.len(),
+        })?;
+        len.write_abstract_bits(writer)?;
+        for element in &self.items {
+            element.write_abstract_bits(writer)?;
+        }
+        Ok(())
+    }
+    fn read_abstract_bits(reader: &mut BitReader) -> Result<Self, FromBytesError>
+    where
+        Self: Sized,
+    {
+        let len = L::read_abstract_bits(reader)?.to_len();
+        let items = (0..len)
+            .map(|_| T::read_abstract_bits(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from(items))
+    }
+}
@@ -0,0 +1,309 @@
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::{
+    AbstractBits, BitReader, BitWriter, FromBytesError, LengthPrefix, ReadErrorCause,
+    Tagged, ToBytesError, UnexpectedEndOfBits,
+};
+
+/// Integers that can store the tag of a [`Tlv`]
+pub trait TlvTag: AbstractBits + Sized {
+    /// Largest tag that fits
+    const MAX_TAG: usize;
+    /// `None` if the tag does not fit
+    fn from_tag(tag: usize) -> Option<Self>;
+    fn to_tag(&self) -> usize;
+}
+
+macro_rules! impl_tlv_tag_for_core_int {
+    ($($type:ty),+) => {$(
+        impl TlvTag for $type {
+            const MAX_TAG: usize = if <$type>::MAX as u128 > usize::MAX as u128 {
+                usize::MAX
+            } else {
+                <$type>::MAX as usize
+            };
+
+            fn from_tag(tag: usize) -> Option<Self> {
+                tag.try_into().ok()
+            }
+            fn to_tag(&self) -> usize {
+                *self as usize
+            }
+        }
+    )+};
+}
+
+impl_tlv_tag_for_core_int! {u8, u16, u32, u64}
+
+macro_rules! impl_tlv_tag_for_UInt {
+    ($($base_type:ty),+) => {$(
+        impl<const N: usize> TlvTag for arbitrary_int::UInt<$base_type, N> {
+            const MAX_TAG: usize = if N >= usize::BITS as usize {
+                usize::MAX
+            } else {
+                (1 << N) - 1
+            };
+
+            fn from_tag(tag: usize) -> Option<Self> {
+                Self::try_new(tag.try_into().ok()?).ok()
+            }
+            fn to_tag(&self) -> usize {
+                self.value() as usize
+            }
+        }
+    )+};
+}
+
+impl_tlv_tag_for_UInt! {u8, u16, u32, u64}
+
+/// What the length of a [`Tlv`] counts
+pub trait LengthUnit {
+    const BITS: usize;
+}
+
+/// The length of a [`Tlv`] counts bytes of the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bytes;
+
+impl LengthUnit for Bytes {
+    const BITS: usize = 8;
+}
+
+/// The length of a [`Tlv`] counts bits of the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bits;
+
+impl LengthUnit for Bits {
+    const BITS: usize = 1;
+}
+
+/// The value of a [`Tlv`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TlvEntry<T> {
+    /// A tag `T` has a variant for
+    Known(T),
+    /// Any other tag with its value as it was read, padded to whole bytes.
+    /// Only the first `bits` bits are written back, missing bytes are zero.
+    Unknown {
+        tag: usize,
+        value: Vec<u8>,
+        bits: usize,
+    },
+}
+
+/// A type-length-value entry: a tag stored as `Tag`, the length of the value
+/// stored as `Len` counting `Unit`s, then the value. Tags are dispatched to
+/// the variants of the enum `T`, other tags are kept as raw bytes so they are
+/// written back unchanged. Known values are padded to a whole `Unit`.
+///
+/// A list of these can end with the input, `#[abstract_bits(rest)]`, or have a
+/// controller like any other list.
+///
+/// # Example
+/// ```
+/// # use abstract_bits::{abstract_bits, AbstractBits, Tlv, TlvEntry};
+/// #[abstract_bits(bits = 8)]
+/// #[derive(Debug, PartialEq, Eq)]
+/// enum Setting {
+///     Channel(u8) = 1,
+///     Name(#[abstract_bits(rest)] Vec<u8>) = 2,
+/// }
+///
+/// let settings: Vec<Tlv<u8, u8, Setting>> = vec![
+///     Tlv::known(Setting::Name(b"ab".to_vec())),
+///     Tlv::unknown(7, vec![0xff]),
+/// ];
+/// let bytes: Vec<u8> = settings
+///     .iter()
+///     .flat_map(|tlv| tlv.to_abstract_bits().unwrap())
+///     .collect();
+/// assert_eq!(bytes, [2, 2, b'a', b'b', 7, 1, 0xff]);
+///
+/// let tlv = Tlv::<u8, u8, Setting>::from_abstract_bits(&bytes[4..]).unwrap();
+/// assert_eq!(
+///     *tlv,
+///     TlvEntry::Unknown {
+///         tag: 7,
+///         value: vec![0xff],
+///         bits: 8
+///     }
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tlv<Tag, Len, T, Unit = Bytes> {
+    entry: TlvEntry<T>,
+    format: Format<Tag, Len, Unit>,
+}
+
+/// Only used as types, a [`Tlv`] stores none of them
+type Format<Tag, Len, Unit> = PhantomData<fn() -> (Tag, Len, Unit)>;
+
+impl<Tag, Len, T, Unit> Tlv<Tag, Len, T, Unit> {
+    pub fn known(value: T) -> Self {
+        Self::from(TlvEntry::Known(value))
+    }
+    /// An entry with a tag `T` has no variant for, its value is whole bytes.
+    /// Writing it fails with [`ToBytesError::KnownDiscriminant`] if `T` does
+    /// have a variant for the tag.
+    pub fn unknown(tag: usize, value: Vec<u8>) -> Self {
+        let bits = value.len() * 8;
+        Self::from(TlvEntry::Unknown { tag, value, bits })
+    }
+    pub fn into_entry(self) -> TlvEntry<T> {
+        self.entry
+    }
+}
+
+impl<Tag, Len, T, Unit> From<TlvEntry<T>> for Tlv<Tag, Len, T, Unit> {
+    fn from(entry: TlvEntry<T>) -> Self {
+        Self {
+            entry,
+            format: PhantomData,
+        }
+    }
+}
+
+impl<Tag, Len, T, Unit> Deref for Tlv<Tag, Len, T, Unit> {
+    type Target = TlvEntry<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.entry
+    }
+}
+
+impl<Tag, Len, T, Unit> DerefMut for Tlv<Tag, Len, T, Unit> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entry
+    }
+}
+
+impl<Tag, Len, T, Unit> AbstractBits for Tlv<Tag, Len, T, Unit>
+where
+    Tag: TlvTag,
+    Len: LengthPrefix,
+    T: Tagged,
+    Unit: LengthUnit,
+{
+    const MIN_BITS: usize = Tag::MIN_BITS + Len::MIN_BITS;
+    const MAX_BITS: usize = Tag::MAX_BITS
+        .saturating_add(Len::MAX_BITS)
+        .saturating_add(Len::MAX_LEN.saturating_mul(Unit::BITS));
+
+    fn write_abstract_bits(&self, writer: &mut BitWriter) -> Result<(), ToBytesError> {
+        let tag = match &self.entry {
+            TlvEntry::Known(value) => value.tag(),
+            TlvEntry::Unknown { tag, .. } if T::has_variant(*tag) => {
+                // it would be read back as that variant
+                return Err(ToBytesError::KnownDiscriminant {
+                    field_name: "tag",
+                    struct_name: core::any::type_name::<Self>(),
+                    got: *tag as u64,
+                });
+            }
+            TlvEntry::Unknown { tag, .. } => *tag,
+        };
+        Tag::from_tag(tag)
+            .ok_or(ToBytesError::ValueTooLarge {
+                field_name: "tag",
+                struct_name: core::any::type_name::<Self>(),
+                max: Tag::MAX_TAG as u64,
+                got: tag as u64,
+            })?
+            .write_abstract_bits(writer)?;
+
+        // the length is filled in once the value is written
+        let len_pos = writer.pos;
+        writer
+            .skip(Len::MIN_BITS)
+            .map_err(|cause| ToBytesError::BufferTooSmall {
+                ty: core::any::type_name::<Len>(),
+                cause,
+            })?;
+        let value_pos = writer.pos;
+        match &self.entry {
+            TlvEntry::Known(value) => value.write_body(writer)?,
+            TlvEntry::Unknown { value, bits, .. } => {
+                for i in 0..bits.div_ceil(8) {
+                    let byte = value.get(i).copied().unwrap_or(0);
+                    writer
+                        .write_u8((bits - i * 8).min(8), byte)
+                        .map_err(|cause| ToBytesError::BufferTooSmall {
+                            ty: core::any::type_name::<Self>(),
+                            cause,
+                        })?;
+                }
+            }
+        }
+        let len = (writer.pos - value_pos).div_ceil(Unit::BITS);
+        let padding = len * Unit::BITS - (writer.pos - value_pos);
+        writer
+            .skip(padding)
+            .map_err(|cause| ToBytesError::BufferTooSmall {
+                ty: core::any::type_name::<Self>(),
+                cause,
+            })?;
+
+        let end = writer.pos;
+        writer.pos = len_pos;
+        Len::from_len(len)
+            .ok_or(ToBytesError::ValueTooLarge {
+                field_name: "length",
+                struct_name: core::any::type_name::<Self>(),
+                max: Len::MAX_LEN as u64,
+                got: len as u64,
+            })?
+            .write_abstract_bits(writer)?;
+        writer.pos = end;
+        Ok(())
+    }
+
+    fn read_abstract_bits(reader: &mut BitReader) -> Result<Self, FromBytesError>
+    where
+        Self: Sized,
+    {
+        let tag = Tag::read_abstract_bits(reader)?.to_tag();
+        let len_bits = Len::read_abstract_bits(reader)?
+            .to_len()
+            .saturating_mul(Unit::BITS);
+        if len_bits > reader.bits_remaining() {
+            return Err(FromBytesError::ReadPrimitive(
+                ReadErrorCause::NotEnoughInput {
+                    ty: core::any::type_name::<Self>(),
+                    cause: UnexpectedEndOfBits {
+                        n_bits: len_bits,
+                        bits_needed: len_bits - reader.bits_remaining(),
+                    },
+                },
+            ));
+        }
+        let mut value = BitReader {
+            pos: 0,
+            buf: &reader.buf[reader.pos..reader.pos + len_bits],
+        };
+        reader.pos += len_bits;
+
+        if !T::has_variant(tag) {
+            let mut raw = Vec::with_capacity(len_bits.div_ceil(8));
+            while value.bits_remaining() > 0 {
+                let n_bits = value.bits_remaining().min(8);
+                raw.push(value.read_u8(n_bits).expect("checked bits remaining"));
+            }
+            return Ok(Self::from(TlvEntry::Unknown {
+                tag,
+                value: raw,
+                bits: len_bits,
+            }));
+        }
+
+        let known = T::read_body(tag, &mut value)?;
+        if value.bits_remaining() >= Unit::BITS {
+            return Err(FromBytesError::TlvLengthMismatch {
+                ty: core::any::type_name::<Self>(),
+                tag,
+                len_bits,
+                read_bits: value.bits_read(),
+            });
+        }
+        Ok(Self::known(known))
+    }
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "bitflags")]
+
+use abstract_bits::{AbstractBits, abstract_bits};
+
+abstract_bits::bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Options: u8 {
+        const ACK = 1;
+        const SECURE = 1 << 1;
+    }
+}
+abstract_bits::impl_abstract_bits_for_bitflags!(Options);
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    options: Options,
+    counter: u8,
+}
+
+#[test]
+fn unknown_bits_are_kept() {
+    let bytes = [0b1000_0010, 7];
+    let frame = Frame::from_abstract_bits(&bytes).unwrap();
+    assert!(frame.options.contains(Options::SECURE));
+    assert_eq!(frame.options.bits(), 0b1000_0010);
+    assert_eq!(frame.to_abstract_bits().unwrap(), bytes);
+}
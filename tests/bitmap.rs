@@ -0,0 +1,38 @@
+use abstract_bits::bitvec::prelude::*;
+use abstract_bits::{AbstractBits, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct NeighborTable {
+    channels: BitArray<[u8; 2]>,
+    #[abstract_bits(length_of = neighbors)]
+    reserved: u4,
+    neighbors: BitVec<u8>,
+}
+
+#[test]
+fn round_trip() {
+    assert_eq!(NeighborTable::MIN_BITS, 16 + 4);
+    assert_eq!(NeighborTable::MAX_BITS, 16 + 4 + 16);
+    let mut channels = BitArray::ZERO;
+    channels.set(0, true);
+    channels.set(15, true);
+    let table = NeighborTable {
+        channels,
+        neighbors: bitvec![u8, Lsb0; 1, 0, 1, 1, 0],
+    };
+    let bytes = table.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x01, 0x80, 0xd5, 0x00]);
+    assert_eq!(NeighborTable::from_abstract_bits(&bytes).unwrap(), table);
+}
+
+#[test]
+fn bit_array_on_its_own() {
+    let bits: BitArray<[u16; 1], Msb0> = BitArray::new([0x8001]);
+    assert_eq!(<BitArray<[u16; 1], Msb0>>::MAX_BITS, 16);
+    let bytes = bits.to_abstract_bits().unwrap();
+    assert_eq!(
+        BitArray::<[u16; 1], Msb0>::from_abstract_bits(&bytes).unwrap(),
+        bits
+    );
+}
@@ -0,0 +1,56 @@
+use abstract_bits::{AbstractBits, FromBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    data: [u8; 9],
+    #[abstract_bits(checksum = crc16_kermit)]
+    reserved: u16,
+}
+
+#[test]
+fn trailing_crc() {
+    let frame = Frame {
+        data: *b"123456789",
+    };
+    let bytes = frame.to_abstract_bits().unwrap();
+    assert_eq!(bytes[9..], [0x89, 0x21]);
+    assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), frame);
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Packet {
+    #[abstract_bits(checksum = xor8, over = kind..=len)]
+    reserved: u8,
+    kind: u4,
+    flags: u4,
+    len: u8,
+    #[abstract_bits(checksum = sum8, over = payload..)]
+    reserved: u8,
+    payload: [u8; 2],
+}
+
+#[test]
+fn checksums_in_front() {
+    let packet = Packet {
+        kind: 1,
+        flags: 2,
+        len: 2,
+        payload: [3, 4],
+    };
+    let bytes = packet.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x21 ^ 2, 0x21, 2, 3 + 4, 3, 4]);
+    assert_eq!(Packet::from_abstract_bits(&bytes).unwrap(), packet);
+
+    let mut corrupted = bytes.clone();
+    corrupted[5] = 5;
+    assert_eq!(
+        Packet::from_abstract_bits(&corrupted),
+        Err(FromBytesError::ChecksumMismatch {
+            struct_name: "Packet",
+            expected: 3 + 5,
+            found: 3 + 4,
+        })
+    );
+}
@@ -0,0 +1,55 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FrameType {
+    Beacon,
+    Data,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    version: u4,
+    frame_type: FrameType,
+    reserved: u3,
+    #[abstract_bits(if = *version >= 2)]
+    sequence: Option<u8>,
+    #[abstract_bits(if = *frame_type == FrameType::Data)]
+    payload: Option<u16>,
+}
+
+#[test]
+fn condition_on_earlier_fields() {
+    let header = Header {
+        version: 2,
+        frame_type: FrameType::Data,
+        sequence: Some(9),
+        payload: Some(0x1234),
+    };
+    let bytes = header.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x12, 9, 0x34, 0x12]);
+    assert_eq!(Header::from_abstract_bits(&bytes).unwrap(), header);
+
+    let header = Header::from_abstract_bits(&[0x01]).unwrap();
+    assert_eq!(header.sequence, None);
+    assert_eq!(header.payload, None);
+}
+
+#[test]
+fn inconsistent_option_is_rejected() {
+    let header = Header {
+        version: 1,
+        frame_type: FrameType::Beacon,
+        sequence: Some(9),
+        payload: None,
+    };
+    assert_eq!(
+        header.to_abstract_bits(),
+        Err(ToBytesError::PresenceMismatch {
+            field_name: "sequence",
+            struct_name: "Header",
+            expected_some: false,
+        })
+    );
+}
@@ -0,0 +1,59 @@
+use abstract_bits::{AbstractBits, FromBytesError, ReadErrorCause, abstract_bits};
+
+#[abstract_bits(bits = 4)]
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Command {
+    Leave = 0,
+    Rejoin(bool, u3) = 1,
+    Route {
+        #[abstract_bits(length_of = hops)]
+        reserved: u4,
+        hops: Vec<u16>,
+        destination: u16,
+    } = 5,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    sequence: u8,
+    command: Command,
+}
+
+#[test]
+fn size() {
+    assert_eq!(Command::MIN_BITS, 4);
+    assert_eq!(Command::MAX_BITS, 4 + 4 + 16 * 16 + 16);
+}
+
+#[test]
+fn round_trip() {
+    for command in [
+        Command::Leave,
+        Command::Rejoin(true, 5),
+        Command::Route {
+            hops: vec![0x1234, 0x5678],
+            destination: 0xABCD,
+        },
+    ] {
+        let frame = Frame {
+            sequence: 42,
+            command,
+        };
+        let bytes = frame.to_abstract_bits().unwrap();
+        assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), frame);
+    }
+}
+
+#[test]
+fn unknown_discriminant() {
+    let err = Command::from_abstract_bits(&[0x03]).unwrap_err();
+    assert!(matches!(
+        err,
+        FromBytesError::ReadEnum {
+            cause: ReadErrorCause::InvalidDiscriminant { got: 3, .. },
+            ..
+        }
+    ));
+}
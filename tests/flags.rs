@@ -0,0 +1,63 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+#[abstract_bits(flags, bits = 8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Capabilities {
+    /// Wakes up for incoming frames
+    Wakeup,
+    LowPower,
+    RxOnWhenIdle = 3,
+    HTTPServer = 6,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Node {
+    capabilities: Capabilities,
+    reserved: u4,
+    id: u4,
+}
+
+#[test]
+fn set_operations() {
+    let mut caps = Capabilities::WAKEUP | Capabilities::RX_ON_WHEN_IDLE;
+    assert!(caps.contains(Capabilities::WAKEUP));
+    assert!(!caps.intersects(Capabilities::LOW_POWER | Capabilities::HTTP_SERVER));
+    caps.insert(Capabilities::HTTP_SERVER);
+    caps -= Capabilities::WAKEUP;
+    assert_eq!(caps.bits(), 0b0100_1000);
+    assert_eq!(!caps, Capabilities::WAKEUP | Capabilities::LOW_POWER);
+    assert_eq!(
+        format!("{caps:?}"),
+        "Capabilities(RX_ON_WHEN_IDLE | HTTP_SERVER)"
+    );
+    assert_eq!(
+        format!("{:?}", Capabilities::empty()),
+        "Capabilities(empty)"
+    );
+}
+
+#[test]
+fn unknown_bits_are_kept() {
+    let bytes = [0b1000_0011, 0xA0];
+    let node = Node::from_abstract_bits(&bytes).unwrap();
+    assert_eq!(
+        format!("{:?}", node.capabilities),
+        "Capabilities(WAKEUP | LOW_POWER | 0x80)"
+    );
+    assert_eq!(node.to_abstract_bits().unwrap(), bytes);
+}
+
+#[abstract_bits(flags, bits = 4)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Options {
+    Flags,
+    Verbose,
+}
+
+#[test]
+fn own_debug_and_flag_names() {
+    let options = Options::FLAGS | Options::VERBOSE;
+    assert_eq!(options.bits(), 0b11);
+    assert_eq!(format!("{options:?}"), "Options(3)");
+}
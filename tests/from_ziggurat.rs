@@ -1,5 +1,5 @@
+//! These tests where taken and adapted from the `zigpy/ziggurat` project.
 use abstract_bits::{AbstractBits, abstract_bits};
-/// These tests where taken and adapted from the `zigpy/ziggurat` project.
 
 /// Zigbee spec 3.4.2 Route Reply Command
 #[abstract_bits]
@@ -0,0 +1,76 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Command {
+    id: u8,
+    #[abstract_bits(if_remaining)]
+    radius: Option<u8>,
+    #[abstract_bits(if_remaining)]
+    options: Option<u16>,
+}
+
+#[test]
+fn present_when_input_remains() {
+    let full = Command {
+        id: 1,
+        radius: Some(2),
+        options: Some(0x0403),
+    };
+    let bytes = full.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [1, 2, 3, 4]);
+    assert_eq!(Command::from_abstract_bits(&bytes).unwrap(), full);
+
+    let short = Command::from_abstract_bits(&[1, 2]).unwrap();
+    assert_eq!(short.radius, Some(2));
+    assert_eq!(short.options, None);
+    assert_eq!(short.to_abstract_bits().unwrap(), [1, 2]);
+    // a trailing byte too short for options is not read
+    assert_eq!(
+        Command::from_abstract_bits(&[1, 2, 3]).unwrap().options,
+        None
+    );
+}
+
+#[test]
+fn gap_is_rejected() {
+    let command = Command {
+        id: 1,
+        radius: None,
+        options: Some(5),
+    };
+    assert!(matches!(
+        command.to_abstract_bits(),
+        Err(ToBytesError::PresenceMismatch {
+            field_name: "options",
+            ..
+        })
+    ));
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Unaligned {
+    kind: u4,
+    #[abstract_bits(if_remaining)]
+    value: Option<u8>,
+}
+
+#[test]
+fn padding_is_not_read_as_a_field() {
+    let none = Unaligned {
+        kind: 3,
+        value: None,
+    };
+    let bytes = none.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x03]);
+    assert_eq!(Unaligned::from_abstract_bits(&bytes).unwrap(), none);
+
+    let some = Unaligned {
+        kind: 3,
+        value: Some(0xab),
+    };
+    let bytes = some.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0xb3, 0x0a]);
+    assert_eq!(Unaligned::from_abstract_bits(&bytes).unwrap(), some);
+}
@@ -0,0 +1,69 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+const VENDOR: u8 = 0x0A;
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Level {
+    Low,
+    Medium,
+    High = 0b110,
+}
+
+#[abstract_bits(bits = 4)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Source {
+    Local = 0x1,
+    Vendor = VENDOR,
+    VendorExtended,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Setting {
+    level: Level,
+    source: Source,
+    reserved: u1,
+}
+
+#[test]
+fn bits_inferred_from_largest_discriminant() {
+    assert_eq!(Level::MAX_BITS, 3);
+    assert_eq!(Level::Medium as u8, 1);
+    assert_eq!(Setting::MAX_BITS, 8);
+}
+
+#[test]
+fn const_and_implicit_discriminants() {
+    let setting = Setting {
+        level: Level::High,
+        source: Source::VendorExtended,
+    };
+    let bytes = setting.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b0101_1110]);
+    assert_eq!(Setting::from_abstract_bits(&bytes).unwrap(), setting);
+}
+
+#[test]
+fn const_discriminants_cast() {
+    assert_eq!(Source::Local as u8, 1);
+    assert_eq!(Source::Vendor as u8, VENDOR);
+    assert_eq!(Source::VendorExtended as u8, VENDOR + 1);
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u16)]
+enum Wide {
+    A = 0,
+    B = 200,
+}
+
+#[test]
+fn wire_width_follows_bits_not_repr() {
+    assert_eq!(Wide::MAX_BITS, 8);
+    assert_eq!(Wide::B as u16, 200);
+    let bytes = Wide::B.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [200]);
+    assert_eq!(Wide::from_abstract_bits(&bytes).unwrap(), Wide::B);
+}
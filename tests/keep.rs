@@ -0,0 +1,87 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    #[abstract_bits(presence_of = extra, keep = check)]
+    has_extra: bool,
+    #[abstract_bits(length_of = data, keep = recompute)]
+    count: u7,
+    extra: Option<u8>,
+    data: Vec<u8>,
+}
+
+#[abstract_bits(bits = 2)]
+#[derive(Debug, PartialEq, Eq)]
+enum Body {
+    Ping = 0,
+    Data(u6) = 1,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Tagged {
+    #[abstract_bits(tag_of = body, keep = check)]
+    kind: u2,
+    body: Body,
+}
+
+#[test]
+fn controllers_are_visible() {
+    let frame = Frame::from_abstract_bits(&[0b0000_0101, 9, 1, 2]).unwrap();
+    assert_eq!(
+        frame,
+        Frame {
+            has_extra: true,
+            count: 2,
+            extra: Some(9),
+            data: vec![1, 2],
+        }
+    );
+    assert_eq!(frame.to_abstract_bits().unwrap(), [0b0000_0101, 9, 1, 2]);
+}
+
+#[test]
+fn recompute_or_check() {
+    let stale_count = Frame {
+        has_extra: false,
+        count: 100,
+        extra: None,
+        data: vec![1],
+    };
+    assert_eq!(stale_count.to_abstract_bits().unwrap(), [0b0000_0010, 1]);
+
+    let wrong_flag = Frame {
+        has_extra: false,
+        count: 0,
+        extra: Some(1),
+        data: vec![],
+    };
+    assert_eq!(
+        wrong_flag.to_abstract_bits(),
+        Err(ToBytesError::ControllerMismatch {
+            field_name: "has_extra",
+            controlled: "extra",
+            struct_name: "Frame",
+        })
+    );
+}
+
+#[test]
+fn kept_tag() {
+    let tagged = Tagged::from_abstract_bits(&[0b0000_1101]).unwrap();
+    assert_eq!(tagged.kind, 1);
+    assert_eq!(tagged.body, Body::Data(3));
+    let wrong_kind = Tagged {
+        kind: 0,
+        body: Body::Data(3),
+    };
+    assert_eq!(
+        wrong_kind.to_abstract_bits(),
+        Err(ToBytesError::ControllerMismatch {
+            field_name: "kind",
+            controlled: "body",
+            struct_name: "Tagged",
+        })
+    );
+}
@@ -0,0 +1,42 @@
+use abstract_bits::{AbstractBits, Prefixed, ToBytesError, abstract_bits, u4};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Directory {
+    #[abstract_bits(len_prefix = u8)]
+    names: Vec<Prefixed<u4, u8>>,
+}
+
+#[abstract_bits(bits = 8)]
+#[derive(Debug, PartialEq, Eq)]
+enum Message {
+    Text(#[abstract_bits(len_prefix = u8)] Vec<u8>) = 1,
+}
+
+#[test]
+fn nested_lists() {
+    assert_eq!(Directory::MAX_BITS, 8 + 255 * (4 + 15 * 8),);
+    let directory = Directory {
+        names: vec![Prefixed::from(b"ab".to_vec()), Prefixed::default()],
+    };
+    let bytes = directory.to_abstract_bits().unwrap();
+    // the 4 bit lengths leave every name's bytes unaligned
+    assert_eq!(bytes, [2, 0x12, 0x26, 0x06]);
+    assert_eq!(Directory::from_abstract_bits(&bytes).unwrap(), directory);
+
+    let too_long = Directory {
+        names: vec![Prefixed::from(vec![0; 16])],
+    };
+    assert_eq!(
+        too_long.to_abstract_bits(),
+        Err(ToBytesError::ListTooLong { max: 15, got: 16 })
+    );
+}
+
+#[test]
+fn list_in_variant() {
+    let message = Message::Text(b"hi".to_vec());
+    let bytes = message.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [1, 2, b'h', b'i']);
+    assert_eq!(Message::from_abstract_bits(&bytes).unwrap(), message);
+}
@@ -0,0 +1,117 @@
+use abstract_bits::{AbstractBits, FromBytesError, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct CountMinusOne {
+    #[abstract_bits(length_of = data, encoded = len - 1)]
+    reserved: u2,
+    reserved: u6,
+    data: Vec<u8>,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Words {
+    #[abstract_bits(length_of = data, encoded = len / 2)]
+    reserved: u8,
+    data: Vec<u8>,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Scaled {
+    width: u8,
+    #[abstract_bits(length_of = data, encoded = len / *width as i128, decoded = raw * *width as i128)]
+    reserved: u8,
+    data: Vec<u8>,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Strided {
+    width: u8,
+    #[abstract_bits(length_of = data, encoded = len * *width as i128)]
+    reserved: u8,
+    data: Vec<u8>,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct WithHeader {
+    #[abstract_bits(length_of = data, encoded = len + 2)]
+    reserved: u8,
+    data: Vec<u8>,
+}
+
+#[test]
+fn offset_length() {
+    assert_eq!(CountMinusOne::MAX_BITS, 8 + 4 * 8);
+    let bytes = [0x01, 7, 8];
+    let parsed = CountMinusOne::from_abstract_bits(&bytes).unwrap();
+    assert_eq!(parsed.data, [7, 8]);
+    assert_eq!(parsed.to_abstract_bits().unwrap(), bytes);
+
+    let empty = CountMinusOne { data: Vec::new() };
+    assert_eq!(
+        empty.to_abstract_bits(),
+        Err(ToBytesError::LengthNotEncodable {
+            field_name: "data",
+            struct_name: "CountMinusOne",
+            len: 0,
+        })
+    );
+    let long = CountMinusOne { data: vec![0; 5] };
+    assert_eq!(
+        long.to_abstract_bits(),
+        Err(ToBytesError::ListTooLong { max: 4, got: 5 })
+    );
+}
+
+#[test]
+fn scaled_length() {
+    assert_eq!(Words::MAX_BITS, 8 + 510 * 8);
+    let words = Words::from_abstract_bits(&[2, 1, 2, 3, 4]).unwrap();
+    assert_eq!(words.data, [1, 2, 3, 4]);
+    assert_eq!(words.to_abstract_bits().unwrap(), [2, 1, 2, 3, 4]);
+    let odd = Words {
+        data: vec![1, 2, 3],
+    };
+    assert_eq!(
+        odd.to_abstract_bits(),
+        Err(ToBytesError::LengthNotEncodable {
+            field_name: "data",
+            struct_name: "Words",
+            len: 3,
+        })
+    );
+}
+
+#[test]
+fn length_from_earlier_field() {
+    let scaled = Scaled::from_abstract_bits(&[3, 2, 1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(scaled.data, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(scaled.to_abstract_bits().unwrap(), [3, 2, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(
+        WithHeader::from_abstract_bits(&[1]),
+        Err(FromBytesError::InvalidListLength {
+            field_name: "data",
+            struct_name: "WithHeader",
+            raw: 1,
+        })
+    );
+}
+
+#[test]
+fn zero_divisor_is_invalid_length() {
+    // the length is worked out as `raw / width`
+    let strided = Strided::from_abstract_bits(&[2, 4, 1, 2]).unwrap();
+    assert_eq!(strided.data, [1, 2]);
+    assert_eq!(
+        Strided::from_abstract_bits(&[0, 4, 1, 2]),
+        Err(FromBytesError::InvalidListLength {
+            field_name: "data",
+            struct_name: "Strided",
+            raw: 4,
+        })
+    );
+}
@@ -0,0 +1,29 @@
+use abstract_bits::{AbstractBits, FromBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    #[abstract_bits(magic = 0xA5)]
+    reserved: u8,
+    #[abstract_bits(magic = 2)]
+    version: u4,
+    kind: u4,
+}
+
+#[test]
+fn written_and_checked() {
+    assert_eq!(Header::MAX_BITS, 16);
+    let header = Header { kind: 3 };
+    let bytes = header.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0xa5, 0x32]);
+    assert_eq!(Header::from_abstract_bits(&bytes).unwrap(), header);
+
+    assert_eq!(
+        Header::from_abstract_bits(&[0xa5, 0x31]),
+        Err(FromBytesError::BadMagic {
+            struct_name: "Header",
+            expected: 2,
+            found: 1,
+        })
+    );
+}
@@ -0,0 +1,42 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Reading {
+    #[abstract_bits(none_value = 0xFFFF)]
+    short_address: Option<u16>,
+    #[abstract_bits(none_value = 0x7FF)]
+    temperature: Option<u11>,
+    reserved: u5,
+}
+
+#[test]
+fn sentinel_decodes_to_none() {
+    assert_eq!(Reading::MIN_BITS, 32);
+    let reading = Reading {
+        short_address: None,
+        temperature: Some(0x123),
+    };
+    let bytes = reading.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0xFF, 0xFF, 0x23, 0x01]);
+    assert_eq!(Reading::from_abstract_bits(&bytes).unwrap(), reading);
+
+    let reading = Reading::from_abstract_bits(&[0x01, 0x00, 0xFF, 0x07]).unwrap();
+    assert_eq!(reading.short_address, Some(1));
+    assert_eq!(reading.temperature, None);
+}
+
+#[test]
+fn some_sentinel_is_rejected() {
+    let reading = Reading {
+        short_address: Some(0xFFFF),
+        temperature: None,
+    };
+    assert_eq!(
+        reading.to_abstract_bits(),
+        Err(ToBytesError::ReservedNoneValue {
+            field_name: "short_address",
+            struct_name: "Reading",
+        })
+    );
+}
@@ -0,0 +1,82 @@
+use abstract_bits::{
+    AbstractBits, FromBytesError, ReadErrorCause, Tagged, abstract_bits,
+};
+
+#[abstract_bits(one_hot, bits = 8)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Mode {
+    Sleep,
+    Standby,
+    Active = 5,
+}
+
+#[abstract_bits(thermometer, bits = 4)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Level {
+    Empty,
+    Low,
+    Half = 2,
+    Full = 4,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Status {
+    mode: Mode,
+    level: Level,
+    reserved: u4,
+}
+
+#[test]
+fn roundtrip() {
+    let status = Status {
+        mode: Mode::Active,
+        level: Level::Half,
+    };
+    let bytes = status.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b0010_0000, 0b0000_0011]);
+    assert_eq!(Status::from_abstract_bits(&bytes).unwrap(), status);
+}
+
+#[test]
+fn invalid_codes_are_rejected() {
+    for bits in [0b0000_0000, 0b0001_0001] {
+        let err = Mode::from_abstract_bits(&[bits]).unwrap_err();
+        assert!(matches!(
+            err,
+            FromBytesError::ReadEnum {
+                cause: ReadErrorCause::NotOneHot { got, .. },
+                ..
+            } if got == bits as u64
+        ));
+    }
+    let err = Level::from_abstract_bits(&[0b0101]).unwrap_err();
+    assert!(matches!(
+        err,
+        FromBytesError::ReadEnum {
+            cause: ReadErrorCause::NotThermometer { got: 0b0101, .. },
+            ..
+        }
+    ));
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    #[abstract_bits(tag_of = mode)]
+    reserved: u8,
+    mode: Mode,
+}
+
+#[test]
+fn tag_is_the_code() {
+    assert_eq!(Mode::Active.tag(), 0b0010_0000);
+    assert_eq!(Level::Half.tag(), 0b0011);
+    assert!(Mode::has_variant(0b0010_0000));
+    assert!(!Mode::has_variant(5));
+
+    let header = Header { mode: Mode::Active };
+    let bytes = header.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b0010_0000]);
+    assert_eq!(Header::from_abstract_bits(&bytes).unwrap(), header);
+}
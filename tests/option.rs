@@ -1,4 +1,4 @@
-use abstract_bits::abstract_bits;
+use abstract_bits::{AbstractBits, abstract_bits};
 
 #[abstract_bits]
 #[derive(Clone)]
@@ -7,3 +7,9 @@ struct Type {
     is_maybe_nothing_present: bool,
     maybe_nothing: Option<u8>,
 }
+
+#[test]
+fn absent_option_takes_no_bits() {
+    assert_eq!(Type::MIN_BITS, 1);
+    assert_eq!(Type::MAX_BITS, 9);
+}
@@ -0,0 +1,59 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits(bits = 4)]
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Status {
+    Idle = 0,
+    #[abstract_bits(other)]
+    Unknown(u8),
+    Busy = 1,
+    Error = 7,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Report {
+    status: Status,
+    reserved: u4,
+}
+
+#[test]
+fn unknown_value_is_kept() {
+    let report = Report::from_abstract_bits(&[0x0C]).unwrap();
+    assert_eq!(report.status, Status::Unknown(12));
+    assert_eq!(report.to_abstract_bits().unwrap(), [0x0C]);
+
+    let report = Report::from_abstract_bits(&[0x07]).unwrap();
+    assert_eq!(report.status, Status::Error);
+}
+
+#[test]
+fn raw_value_must_fit() {
+    let report = Report {
+        status: Status::Unknown(16),
+    };
+    assert!(matches!(
+        report.to_abstract_bits(),
+        Err(ToBytesError::ValueTooLarge {
+            max: 15,
+            got: 16,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn raw_value_can_not_be_known() {
+    let report = Report {
+        status: Status::Unknown(7),
+    };
+    assert_eq!(
+        report.to_abstract_bits(),
+        Err(ToBytesError::KnownDiscriminant {
+            field_name: "Unknown",
+            struct_name: "Status",
+            got: 7,
+        })
+    );
+}
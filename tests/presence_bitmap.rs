@@ -0,0 +1,42 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Beacon {
+    #[abstract_bits(presence_bitmap = (battery, rssi, position))]
+    reserved: u4,
+    kind: u4,
+    battery: Option<u8>,
+    rssi: Option<u8>,
+    position: Option<u16>,
+}
+
+#[test]
+fn bits_select_options() {
+    assert_eq!(Beacon::MIN_BITS, 8);
+    assert_eq!(Beacon::MAX_BITS, 8 + 8 + 8 + 16);
+    let beacon = Beacon {
+        kind: 3,
+        battery: Some(0x55),
+        rssi: None,
+        position: Some(0x1234),
+    };
+    let bytes = beacon.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x35, 0x55, 0x34, 0x12]);
+    assert_eq!(Beacon::from_abstract_bits(&bytes).unwrap(), beacon);
+}
+
+#[test]
+fn unlisted_bits_are_reserved() {
+    let beacon = Beacon::from_abstract_bits(&[0x38]).unwrap();
+    assert_eq!(
+        beacon,
+        Beacon {
+            kind: 3,
+            battery: None,
+            rssi: None,
+            position: None,
+        }
+    );
+    assert_eq!(beacon.to_abstract_bits().unwrap(), [0x30]);
+}
@@ -0,0 +1,39 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    #[abstract_bits(absence_of = source)]
+    reserved: bool,
+    #[abstract_bits(presence_of = destination, present = 1 | 3)]
+    reserved: u2,
+    reserved: u5,
+    source: Option<u8>,
+    destination: Option<u8>,
+}
+
+#[test]
+fn absence_flag() {
+    let frame = Frame {
+        source: Some(7),
+        destination: None,
+    };
+    let bytes = frame.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b000, 7]);
+    assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), frame);
+}
+
+#[test]
+fn several_present_values() {
+    let frame = Frame {
+        source: None,
+        destination: Some(9),
+    };
+    assert_eq!(frame.to_abstract_bits().unwrap(), [0b011, 9]);
+
+    // 3 also means present, 2 means absent
+    let frame = Frame::from_abstract_bits(&[0b111, 4]).unwrap();
+    assert_eq!(frame.destination, Some(4));
+    let frame = Frame::from_abstract_bits(&[0b101]).unwrap();
+    assert_eq!(frame.destination, None);
+}
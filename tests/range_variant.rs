@@ -0,0 +1,64 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits(bits = 3)]
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Mode {
+    Off = 0,
+    On = 1,
+    #[abstract_bits(range = 2..=3)]
+    Vendor,
+    #[abstract_bits(range = 4..=7)]
+    Reserved(u8),
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Config {
+    mode: Mode,
+    reserved: u5,
+}
+
+#[test]
+fn range_maps_to_variant() {
+    let config = Config::from_abstract_bits(&[0x06]).unwrap();
+    assert_eq!(config.mode, Mode::Reserved(6));
+    assert_eq!(config.to_abstract_bits().unwrap(), [0x06]);
+
+    let config = Config::from_abstract_bits(&[0x03]).unwrap();
+    assert_eq!(config.mode, Mode::Vendor);
+    // a range without a raw value is written as its first value
+    assert_eq!(config.to_abstract_bits().unwrap(), [0x02]);
+}
+
+#[test]
+fn raw_value_must_be_in_range() {
+    let config = Config {
+        mode: Mode::Reserved(1),
+    };
+    assert!(matches!(
+        config.to_abstract_bits(),
+        Err(ToBytesError::ValueOutOfRange {
+            min: 4,
+            max: 7,
+            got: 1,
+            ..
+        })
+    ));
+}
+
+#[abstract_bits(bits = 3)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Level {
+    Off = 0,
+    #[abstract_bits(range = 2..=3)]
+    Vendor,
+    High = 5,
+}
+
+#[test]
+fn fieldless_range_casts_to_its_start() {
+    assert_eq!(Level::Off as u8, 0);
+    assert_eq!(Level::Vendor as u8, 2);
+    assert_eq!(Level::High as u8, 5);
+}
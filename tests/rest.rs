@@ -0,0 +1,65 @@
+use abstract_bits::{AbstractBits, FromBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    kind: u4,
+    reserved: u4,
+    #[abstract_bits(rest)]
+    payload: Vec<u8>,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Samples {
+    count: u8,
+    #[abstract_bits(rest)]
+    samples: Vec<u16>,
+}
+
+#[test]
+fn reads_until_input_ends() {
+    assert_eq!(Frame::MAX_BITS, usize::MAX);
+    let frame = Frame::from_abstract_bits(&[0x03, 1, 2, 3]).unwrap();
+    assert_eq!(frame.kind, 3);
+    assert_eq!(frame.payload, [1, 2, 3]);
+    assert_eq!(frame.to_abstract_bits().unwrap(), [0x03, 1, 2, 3]);
+
+    let large = Frame {
+        kind: 1,
+        payload: vec![7; 5000],
+    };
+    let bytes = large.to_abstract_bits().unwrap();
+    assert_eq!(bytes.len(), 5001);
+    assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), large);
+}
+
+#[test]
+fn partial_element_is_an_error() {
+    let err = Samples::from_abstract_bits(&[2, 0x01, 0x00, 0x02]).unwrap_err();
+    assert_eq!(
+        err,
+        FromBytesError::LeftoverBits {
+            field_name: "samples",
+            struct_name: "Samples",
+            n_bits: 8,
+        }
+    );
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Readings {
+    #[abstract_bits(rest)]
+    values: Vec<u12>,
+}
+
+#[test]
+fn padding_is_not_read_as_an_element() {
+    let readings = Readings {
+        values: vec![1, 2, 3],
+    };
+    let bytes = readings.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x01, 0x20, 0x00, 0x03, 0x00]);
+    assert_eq!(Readings::from_abstract_bits(&bytes).unwrap(), readings);
+}
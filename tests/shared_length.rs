@@ -0,0 +1,39 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Routes {
+    #[abstract_bits(length_of = (addresses, costs))]
+    reserved: u8,
+    addresses: Vec<u16>,
+    costs: Vec<u8>,
+}
+
+#[test]
+fn lists_share_length() {
+    let routes = Routes {
+        addresses: vec![0x1234, 0x5678],
+        costs: vec![1, 2],
+    };
+    let bytes = routes.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [2, 0x34, 0x12, 0x78, 0x56, 1, 2]);
+    assert_eq!(Routes::from_abstract_bits(&bytes).unwrap(), routes);
+}
+
+#[test]
+fn different_lengths_are_an_error() {
+    let routes = Routes {
+        addresses: vec![0x1234, 0x5678],
+        costs: vec![1],
+    };
+    assert_eq!(
+        routes.to_abstract_bits(),
+        Err(ToBytesError::ListLengthMismatch {
+            field_name: "addresses",
+            other_field: "costs",
+            struct_name: "Routes",
+            len: 2,
+            other_len: 1,
+        })
+    );
+}
@@ -0,0 +1,64 @@
+use abstract_bits::{AbstractBits, Tagged, ToBytesError, abstract_bits};
+
+#[abstract_bits(bits = 8)]
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Body {
+    Beacon { order: u4, reserved: u4 } = 0,
+    Data(u16) = 1,
+    Ack = 2,
+    Command(u8) = 9,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    #[abstract_bits(tag_of = body)]
+    reserved: u3,
+    security: bool,
+    reserved: u4,
+    sequence: u8,
+    body: Body,
+}
+
+#[test]
+fn size() {
+    assert_eq!(Body::MIN_BODY_BITS, 0);
+    assert_eq!(Body::MAX_BODY_BITS, 16);
+    assert_eq!(Frame::MIN_BITS, 16);
+    assert_eq!(Frame::MAX_BITS, 32);
+}
+
+#[test]
+fn tag_in_header() {
+    let frame = Frame {
+        security: true,
+        sequence: 7,
+        body: Body::Data(0xABCD),
+    };
+    let bytes = frame.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b0000_1001, 7, 0xCD, 0xAB]);
+    assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), frame);
+
+    let frame = Frame {
+        security: false,
+        sequence: 8,
+        body: Body::Ack,
+    };
+    let bytes = frame.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0b0000_0010, 8]);
+    assert_eq!(Frame::from_abstract_bits(&bytes).unwrap(), frame);
+}
+
+#[test]
+fn tag_does_not_fit() {
+    let frame = Frame {
+        security: false,
+        sequence: 8,
+        body: Body::Command(1),
+    };
+    assert!(matches!(
+        frame.to_abstract_bits(),
+        Err(ToBytesError::ValueTooLarge { max: 7, got: 9, .. })
+    ));
+}
@@ -0,0 +1,71 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Name {
+    #[abstract_bits(terminator = 0)]
+    chars: Vec<u8>,
+    checksum: u8,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Entry {
+    value: u7,
+    #[abstract_bits(is_last)]
+    last: bool,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Entries {
+    #[abstract_bits(until_last)]
+    entries: Vec<Entry>,
+    trailer: u8,
+}
+
+#[test]
+fn terminator_ends_list() {
+    let name = Name::from_abstract_bits(&[b'a', b'b', 0, 9]).unwrap();
+    assert_eq!(name.chars, b"ab");
+    assert_eq!(name.checksum, 9);
+    assert_eq!(name.to_abstract_bits().unwrap(), [b'a', b'b', 0, 9]);
+
+    let invalid = Name {
+        chars: vec![b'a', 0],
+        checksum: 0,
+    };
+    assert_eq!(
+        invalid.to_abstract_bits(),
+        Err(ToBytesError::TerminatorInList {
+            field_name: "chars",
+            struct_name: "Name",
+        })
+    );
+}
+
+#[test]
+fn last_flag_ends_list() {
+    let entries = Entries::from_abstract_bits(&[0x01, 0x02, 0x83, 0xff]).unwrap();
+    assert_eq!(
+        entries.entries,
+        [Entry { value: 1 }, Entry { value: 2 }, Entry { value: 3 }]
+    );
+    assert_eq!(entries.trailer, 0xff);
+    assert_eq!(
+        entries.to_abstract_bits().unwrap(),
+        [0x01, 0x02, 0x83, 0xff]
+    );
+
+    let empty = Entries {
+        entries: Vec::new(),
+        trailer: 0,
+    };
+    assert_eq!(
+        empty.to_abstract_bits(),
+        Err(ToBytesError::EmptyList {
+            field_name: "entries",
+            struct_name: "Entries",
+        })
+    );
+}
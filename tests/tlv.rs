@@ -0,0 +1,95 @@
+use abstract_bits::{
+    AbstractBits, Bits, FromBytesError, Tlv, TlvEntry, ToBytesError, abstract_bits, u4,
+};
+
+#[abstract_bits(bits = 8)]
+#[derive(Debug, PartialEq, Eq)]
+enum Setting {
+    Channel(u8) = 1,
+    PanId(u16) = 2,
+}
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    version: u8,
+    #[abstract_bits(rest)]
+    settings: Vec<Tlv<u8, u8, Setting>>,
+}
+
+#[test]
+fn unknown_tags_round_trip() {
+    let bytes = [1, 2, 2, 0x34, 0x12, 9, 3, 0xaa, 0xbb, 0xcc, 1, 1, 11];
+    let frame = Frame::from_abstract_bits(&bytes).unwrap();
+    assert_eq!(
+        frame,
+        Frame {
+            version: 1,
+            settings: vec![
+                Tlv::known(Setting::PanId(0x1234)),
+                Tlv::unknown(9, vec![0xaa, 0xbb, 0xcc]),
+                Tlv::known(Setting::Channel(11)),
+            ],
+        }
+    );
+    assert_eq!(frame.to_abstract_bits().unwrap(), bytes);
+}
+
+#[test]
+fn length_must_match_value() {
+    // a channel takes one byte, not two
+    assert_eq!(
+        Frame::from_abstract_bits(&[1, 1, 2, 11, 0]),
+        Err(FromBytesError::TlvLengthMismatch {
+            ty: core::any::type_name::<Tlv<u8, u8, Setting>>(),
+            tag: 1,
+            len_bits: 16,
+            read_bits: 8,
+        })
+    );
+}
+
+#[abstract_bits(bits = 4)]
+#[derive(Debug, PartialEq, Eq)]
+enum Flag {
+    Urgent(bool) = 1,
+}
+
+#[test]
+fn length_in_bits() {
+    let flag: Tlv<u4, u4, Flag, Bits> = Tlv::known(Flag::Urgent(true));
+    let bytes = flag.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x11, 0x01]);
+    let read = Tlv::<u4, u4, Flag, Bits>::from_abstract_bits(&bytes).unwrap();
+    assert_eq!(*read, TlvEntry::Known(Flag::Urgent(true)));
+}
+
+#[test]
+fn unknown_bits_round_trip() {
+    // tag 5 with a value of three bits
+    let bytes = [0x35, 0x05];
+    let read = Tlv::<u4, u4, Flag, Bits>::from_abstract_bits(&bytes).unwrap();
+    assert_eq!(
+        *read,
+        TlvEntry::Unknown {
+            tag: 5,
+            value: vec![0x05],
+            bits: 3
+        }
+    );
+    assert_eq!(read.to_abstract_bits().unwrap(), bytes);
+}
+
+#[test]
+fn unknown_entry_with_known_tag() {
+    // it would be read back as a channel
+    let tlv = Tlv::<u8, u8, Setting>::unknown(1, vec![0xaa, 0xbb, 0xcc]);
+    assert_eq!(
+        tlv.to_abstract_bits(),
+        Err(ToBytesError::KnownDiscriminant {
+            field_name: "tag",
+            struct_name: core::any::type_name::<Tlv<u8, u8, Setting>>(),
+            got: 1,
+        })
+    );
+}
@@ -0,0 +1,32 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Routes {
+    #[abstract_bits(presence_of = gateway)]
+    reserved: bool,
+    #[abstract_bits(length_of = hops)]
+    reserved: u3,
+    reserved: u4,
+    gateway: Option<(u16, bool)>,
+    hops: Vec<(u16, u8)>,
+    costs: [(bool, u8); 2],
+}
+
+#[test]
+fn size() {
+    assert_eq!(<(u16, u8)>::MIN_BITS, 24);
+    assert_eq!(<(u16, u8, bool)>::MAX_BITS, 25);
+    assert_eq!(Routes::MIN_BITS, 8 + 2 * 9);
+}
+
+#[test]
+fn round_trip() {
+    let routes = Routes {
+        gateway: Some((0x1234, true)),
+        hops: vec![(0xABCD, 3), (0x0001, 7)],
+        costs: [(false, 1), (true, 2)],
+    };
+    let bytes = routes.to_abstract_bits().unwrap();
+    assert_eq!(Routes::from_abstract_bits(&bytes).unwrap(), routes);
+}
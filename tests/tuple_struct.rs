@@ -0,0 +1,31 @@
+use abstract_bits::{AbstractBits, abstract_bits};
+
+/// The controllers and padding do not end up in the struct, the list is
+/// therefore accessed as `.2` and controlled through index 2.
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Neighbors(
+    #[abstract_bits(length_of = 2)] u4,
+    #[abstract_bits(presence_of = 1)] bool,
+    #[abstract_bits(reserved)] u3,
+    u12,
+    Option<u16>,
+    Vec<u8>,
+);
+
+#[test]
+fn size() {
+    assert_eq!(Neighbors::MIN_BITS, 4 + 1 + 3 + 12);
+}
+
+#[test]
+fn round_trip() {
+    let neighbors = Neighbors(0xABC, Some(0x1234), vec![1, 2, 3]);
+    let bytes = neighbors.to_abstract_bits().unwrap();
+    assert_eq!(bytes[0], 0b0001_0011);
+    assert_eq!(Neighbors::from_abstract_bits(&bytes).unwrap(), neighbors);
+
+    let neighbors = Neighbors(0xABC, None, Vec::new());
+    let bytes = neighbors.to_abstract_bits().unwrap();
+    assert_eq!(Neighbors::from_abstract_bits(&bytes).unwrap(), neighbors);
+}
@@ -0,0 +1,76 @@
+use abstract_bits::{AbstractBits, ToBytesError, abstract_bits};
+
+#[abstract_bits]
+#[derive(Debug, PartialEq, Eq)]
+struct Samples {
+    #[abstract_bits(length_of = readings)]
+    reserved: u4,
+    grid: [[u4; 2]; 2],
+    channels: [u12; 2],
+    readings: Vec<u12>,
+    level: u7,
+    reserved: u1,
+}
+
+fn samples() -> Samples {
+    // the fields hold primitives, not `arbitrary_int` types
+    let grid: [[u8; 2]; 2] = [[1, 2], [3, 4]];
+    let channels: [u16; 2] = [0xabc, 0x123];
+    let readings: Vec<u16> = vec![0xfff];
+    let level: u8 = 0x7f;
+    Samples {
+        grid,
+        channels,
+        readings,
+        level,
+    }
+}
+
+#[test]
+fn round_trip() {
+    assert_eq!(Samples::MIN_BITS, 4 + 16 + 24 + 7 + 1);
+    assert_eq!(Samples::MAX_BITS, 4 + 16 + 24 + 16 * 12 + 7 + 1);
+    let samples = samples();
+    let bytes = samples.to_abstract_bits().unwrap();
+    assert_eq!(bytes, [0x11, 0x32, 0xc4, 0xab, 0x23, 0xf1, 0xff, 0x7f]);
+    assert_eq!(Samples::from_abstract_bits(&bytes).unwrap(), samples);
+}
+
+#[test]
+fn out_of_range_values_are_an_error() {
+    let mut samples = samples();
+    samples.grid[1][0] = 0x10;
+    assert_eq!(
+        samples.to_abstract_bits(),
+        Err(ToBytesError::ValueTooLarge {
+            field_name: "grid",
+            struct_name: "Samples",
+            max: 0xf,
+            got: 0x10,
+        })
+    );
+
+    let mut samples = self::samples();
+    samples.readings.push(0x1000);
+    assert_eq!(
+        samples.to_abstract_bits(),
+        Err(ToBytesError::ValueTooLarge {
+            field_name: "readings",
+            struct_name: "Samples",
+            max: 0xfff,
+            got: 0x1000,
+        })
+    );
+
+    let mut samples = self::samples();
+    samples.level = 0x80;
+    assert_eq!(
+        samples.to_abstract_bits(),
+        Err(ToBytesError::ValueTooLarge {
+            field_name: "level",
+            struct_name: "Samples",
+            max: 0x7f,
+            got: 0x80,
+        })
+    );
+}